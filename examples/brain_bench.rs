@@ -0,0 +1,106 @@
+//! Time computing the brains of every bot in a snapshot with the compiled tapes against interpreting the programs
+//! with mli, and check that both give the same outputs.
+//!
+//! cargo run --release --no-default-features --example brain_bench -- <snapshot> [rounds]
+
+extern crate evobots;
+
+use std::time::{Duration, Instant};
+
+use evobots::World;
+use evobots::ins::Arithmetic;
+use evobots::bot::{Bot, Brain};
+use evobots::disasm::{BrainKind, BRAIN_KINDS};
+use evobots::snapshot::Snapshot;
+
+const DEFAULT_ROUNDS: usize = 100;
+// The number of different sets of inputs each brain is computed with every round
+const INPUT_SETS: usize = 8;
+
+fn seconds(d: Duration) -> f64 {
+    d.as_secs() as f64 + d.subsec_nanos() as f64 * 1e-9
+}
+
+/// Inputs spread over small and large magnitudes, like the energies and signals brains see.
+fn inputs(count: usize) -> Vec<Vec<i64>> {
+    let mut state = 0x2545f4914f6cdd1du64;
+    (0..INPUT_SETS)
+        .map(|_| {
+            (0..count)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    (state as i64) >> (state % 56)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn bench(bots: &[&Bot], kind: BrainKind, arithmetic: Arithmetic, rounds: usize) {
+    let inputs = inputs(kind.inputs());
+    let mut expected = vec![0; kind.outputs()];
+    let mut actual = vec![0; kind.outputs()];
+    for bot in bots {
+        for i in &inputs {
+            kind.of(bot).interpret(arithmetic, i, &mut expected);
+            kind.of(bot).compute(arithmetic, i, &mut actual);
+            assert!(expected == actual, "the tape of bot {} differs from its program", bot.id);
+        }
+    }
+
+    let start = Instant::now();
+    for _ in 0..rounds {
+        for bot in bots {
+            for i in &inputs {
+                kind.of(bot).interpret(arithmetic, i, &mut expected);
+            }
+        }
+    }
+    let interpreted = seconds(start.elapsed());
+
+    let start = Instant::now();
+    for _ in 0..rounds {
+        for bot in bots {
+            for i in &inputs {
+                kind.of(bot).compute(arithmetic, i, &mut actual);
+            }
+        }
+    }
+    let compiled = seconds(start.elapsed());
+
+    let instructions = bots.iter().map(|b| kind.of(b).len()).sum::<usize>();
+    let kept = bots.iter().map(|b| kind.of(b).tape().len()).sum::<usize>();
+    println!("{}: {} of {} instructions kept, interpreted {:.3}s, compiled {:.3}s, {:.1}x faster",
+             kind.name(),
+             kept,
+             instructions,
+             interpreted,
+             compiled,
+             interpreted / compiled);
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let path = args.next().unwrap_or_else(|| {
+        println!("usage: brain_bench <snapshot> [rounds]");
+        std::process::exit(1);
+    });
+    let rounds = args.next().and_then(|r| r.parse().ok()).unwrap_or(DEFAULT_ROUNDS);
+
+    let snapshot: Snapshot = Snapshot::load(&path).unwrap_or_else(|e| {
+        println!("Unable to load snapshot {}: {}", path, e);
+        std::process::exit(1);
+    });
+    let world: World = snapshot.restore();
+    let bots = world.deps.node_weights().flat_map(|n| n.bots.iter().map(|b| &**b)).collect::<Vec<_>>();
+    println!("{} bots at tick {}, {} rounds of {} input sets",
+             bots.len(),
+             world.period,
+             rounds,
+             INPUT_SETS);
+    for kind in &BRAIN_KINDS {
+        bench(&bots, *kind, world.config.arithmetic, rounds);
+    }
+}
@@ -0,0 +1,28 @@
+//! Run the same ecology with the default multi expression program brains and with linear genetic program brains, then
+//! print a summary of each.
+//!
+//! cargo run --release --no-default-features --example compare_brains -- [ticks]
+
+extern crate evobots;
+
+use evobots::{SimConfig, World, SEED};
+use evobots::bot::{Brain, MepBrain};
+use evobots::lgp::LgpBrain;
+
+const DEFAULT_TICKS: u64 = 10000;
+
+fn run<B: Brain>(name: &str, ticks: u64) {
+    let mut world: World<B> = World::new(&SEED, SimConfig::default());
+    while world.period < ticks {
+        world.step();
+    }
+    let bots = world.deps.node_weights().map(|n| n.bots.len()).sum::<usize>();
+    print!("{}: Bots: {}, ", name, bots);
+    world.print_summary();
+}
+
+fn main() {
+    let ticks = std::env::args().nth(1).and_then(|t| t.parse().ok()).unwrap_or(DEFAULT_TICKS);
+    run::<MepBrain>("mep", ticks);
+    run::<LgpBrain>("lgp", ticks);
+}
@@ -0,0 +1,55 @@
+use bot::*;
+use disasm::{BrainKind, BRAIN_KINDS};
+
+/// Which parts of a brain's program actually contribute to its outputs.
+#[derive(Clone, Debug)]
+pub struct BrainAnalysis {
+    /// Whether each instruction is used in computing an output.
+    pub used: Vec<bool>,
+    /// Whether each input is read by a used instruction.
+    pub inputs_read: Vec<bool>,
+}
+
+impl BrainAnalysis {
+    /// Analyze a program with `inputs` inputs whose last `outputs` instructions are the outputs.
+    pub fn of_program(program: &[(Ins, usize, usize)], inputs: usize, outputs: usize) -> Self {
+        let len = program.len();
+        let mut analysis = BrainAnalysis {
+            used: vec![false; len],
+            inputs_read: vec![false; inputs],
+        };
+        // The outputs are the last instructions and everything else can only be reached through them
+        let mut stack = (len.saturating_sub(outputs)..len).collect::<Vec<_>>();
+        while let Some(i) = stack.pop() {
+            if analysis.used[i] {
+                continue;
+            }
+            analysis.used[i] = true;
+            let (ref ins, a, b) = program[i];
+            for &operand in [a, b].iter().take(ins.operands()) {
+                if operand < inputs {
+                    analysis.inputs_read[operand] = true;
+                } else {
+                    stack.push(operand - inputs);
+                }
+            }
+        }
+        analysis
+    }
+
+    /// The number of instructions that contribute to an output.
+    pub fn effective_length(&self) -> usize {
+        self.used.iter().filter(|&&u| u).count()
+    }
+
+    /// The number of different inputs that are read.
+    pub fn inputs_read_count(&self) -> usize {
+        self.inputs_read.iter().filter(|&&r| r).count()
+    }
+}
+
+/// The analyses of all three brains of a bot, in the order of `BRAIN_KINDS`.
+pub fn analyze_bot<B: Brain>(bot: &Bot<B>) -> [BrainAnalysis; 3] {
+    let analyze = |kind: BrainKind| kind.of(bot).analyze();
+    [analyze(BRAIN_KINDS[0]), analyze(BRAIN_KINDS[1]), analyze(BRAIN_KINDS[2])]
+}
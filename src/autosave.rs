@@ -0,0 +1,96 @@
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use bot::Brain;
+use snapshot::Snapshot;
+use world::World;
+
+const PREFIX: &'static str = "snapshot-";
+const EXTENSION: &'static str = ".bin";
+const TEMP_EXTENSION: &'static str = ".tmp";
+
+/// Periodically writes snapshots of the world into a directory, keeping only the newest few.
+///
+/// Snapshots are written to a temporary file and renamed into place, so a crash during a write never damages an
+/// existing snapshot. Temporary files left behind by a crash are removed when the autosave is created.
+pub struct Autosave {
+    dir: PathBuf,
+    every: u64,
+    keep: usize,
+}
+
+impl Autosave {
+    pub fn new<P: Into<PathBuf>>(dir: P, every: u64, keep: usize) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            let is_temp = path.file_name()
+                .and_then(|n| n.to_str())
+                .map(|n| n.starts_with(PREFIX) && n.ends_with(TEMP_EXTENSION))
+                .unwrap_or(false);
+            if is_temp {
+                fs::remove_file(path)?;
+            }
+        }
+        Ok(Autosave {
+            dir: dir,
+            every: every,
+            keep: keep,
+        })
+    }
+
+    /// Save the world if it is on a tick that should be saved.
+    pub fn tick<B: Brain>(&self, world: &World<B>) -> io::Result<()> {
+        if self.every != 0 && world.period % self.every == 0 {
+            self.save(world)?;
+            self.rotate()?;
+        }
+        Ok(())
+    }
+
+    fn save<B: Brain>(&self, world: &World<B>) -> io::Result<()> {
+        // Zero padding keeps the names sorted by tick
+        let name = format!("{}{:020}{}", PREFIX, world.period, EXTENSION);
+        let temp = self.dir.join(format!("{}{}", name, TEMP_EXTENSION));
+        {
+            let file = File::create(&temp)?;
+            Snapshot::new(world).write_to(&file)?;
+            file.sync_all()?;
+        }
+        fs::rename(&temp, self.dir.join(name))
+    }
+
+    fn rotate(&self) -> io::Result<()> {
+        let saves = snapshots(&self.dir)?;
+        if saves.len() > self.keep {
+            for old in &saves[..saves.len() - self.keep] {
+                fs::remove_file(old)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// All of the autosaved snapshots in a directory from oldest to newest.
+pub fn snapshots<P: AsRef<Path>>(dir: P) -> io::Result<Vec<PathBuf>> {
+    let mut saves = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let is_save = path.file_name()
+            .and_then(|n| n.to_str())
+            .map(|n| n.starts_with(PREFIX) && n.ends_with(EXTENSION))
+            .unwrap_or(false);
+        if is_save {
+            saves.push(path);
+        }
+    }
+    saves.sort();
+    Ok(saves)
+}
+
+/// The most recent autosaved snapshot in a directory, if there is one.
+pub fn newest<P: AsRef<Path>>(dir: P) -> io::Result<Option<PathBuf>> {
+    Ok(snapshots(dir)?.pop())
+}
@@ -1,17 +1,44 @@
-extern crate mli;
 extern crate rand;
 use self::rand::Rng;
 
-pub type R = rand::isaac::Isaac64Rng;
+use super::config::SimConfig;
+use super::ins::OpcodeTable;
+pub use super::ins::Ins;
+pub use super::brain::{Brain, BrainShape};
+pub use super::mep::{MepBrain, MepState};
+
+pub type R = super::rng::SimRng;
 
 pub mod nodebrain {
     // 0, 1, 2, -1, rand, node energy, present node energy, bot count, present node bot count, self energy, present node connections, node connections, period, force, and memory are inputs.
     pub const STATIC_INPUTS: usize = 14;
     pub const TOTAL_INPUTS: usize = STATIC_INPUTS + super::finalbrain::TOTAL_MEMORY;
     pub const TOTAL_OUTPUTS: usize = 5;
+    pub const RAND: usize = 4;
+    pub const NODE_ENERGY: usize = 5;
+    pub const PRESENT_NODE_ENERGY: usize = 6;
+    pub const NODE_BOTS: usize = 7;
+    pub const PRESENT_NODE_BOTS: usize = 8;
+    pub const SELF_ENERGY: usize = 9;
+    pub const PRESENT_NODE_CONNECTIONS: usize = 10;
+    pub const NODE_CONNECTIONS: usize = 11;
+    pub const PERIOD: usize = 12;
+    pub const FORCE: usize = 13;
+    pub const STATIC_INPUT_NAMES: [&'static str; STATIC_INPUTS] = ["0", "1", "2", "-1", "rand", "node energy",
+        "present node energy", "node bot count", "present node bot count", "self energy",
+        "present node connections", "node connections", "period", "force"];
+    // The rank of the node followed by the values passed to the final brain
+    pub const OUTPUT_NAMES: [&'static str; TOTAL_OUTPUTS] = ["rank", "value 0", "value 1", "value 2", "value 3"];
     pub const DEFAULT_MUTATE_SIZE: usize = 8;
     pub const DEFAULT_CROSSOVER_POINTS: usize = 1;
     pub const DEFAULT_INSTRUCTIONS: usize = 32;
+    pub const SHAPE: ::brain::BrainShape = ::brain::BrainShape {
+        inputs: TOTAL_INPUTS,
+        outputs: TOTAL_OUTPUTS,
+        instructions: DEFAULT_INSTRUCTIONS,
+        mutate_size: DEFAULT_MUTATE_SIZE,
+        crossover_points: DEFAULT_CROSSOVER_POINTS,
+    };
 }
 
 pub mod botbrain {
@@ -19,9 +46,30 @@ pub mod botbrain {
     pub const STATIC_INPUTS: usize = 13;
     pub const TOTAL_INPUTS: usize = STATIC_INPUTS + super::finalbrain::TOTAL_MEMORY;
     pub const TOTAL_OUTPUTS: usize = 5;
+    pub const RAND: usize = 4;
+    pub const PRESENT_NODE_ENERGY: usize = 5;
+    pub const PRESENT_NODE_BOTS: usize = 6;
+    pub const SELF_ENERGY: usize = 7;
+    pub const BOT_ENERGY: usize = 8;
+    pub const BOT_SIGNAL: usize = 9;
+    pub const PRESENT_NODE_CONNECTIONS: usize = 10;
+    pub const PERIOD: usize = 11;
+    pub const FORCE: usize = 12;
+    pub const STATIC_INPUT_NAMES: [&'static str; STATIC_INPUTS] = ["0", "1", "2", "-1", "rand",
+        "present node energy", "present node bot count", "self energy", "bot energy", "bot signal",
+        "present node connections", "period", "force"];
+    // The rank of the bot followed by the values passed to the final brain
+    pub const OUTPUT_NAMES: [&'static str; TOTAL_OUTPUTS] = ["rank", "value 0", "value 1", "value 2", "value 3"];
     pub const DEFAULT_MUTATE_SIZE: usize = 8;
     pub const DEFAULT_CROSSOVER_POINTS: usize = 1;
     pub const DEFAULT_INSTRUCTIONS: usize = 32;
+    pub const SHAPE: ::brain::BrainShape = ::brain::BrainShape {
+        inputs: TOTAL_INPUTS,
+        outputs: TOTAL_OUTPUTS,
+        instructions: DEFAULT_INSTRUCTIONS,
+        mutate_size: DEFAULT_MUTATE_SIZE,
+        crossover_points: DEFAULT_CROSSOVER_POINTS,
+    };
 }
 
 pub mod finalbrain {
@@ -30,6 +78,17 @@ pub mod finalbrain {
     pub const TOTAL_MEMORY: usize = 4;
     // 0, 1, 2, -1, rand, present node energy, bot count, self energy, self index, present node connections, period, force, and memory are inputs
     pub const STATIC_INPUTS: usize = 12;
+    pub const RAND: usize = 4;
+    pub const PRESENT_NODE_ENERGY: usize = 5;
+    pub const PRESENT_NODE_BOTS: usize = 6;
+    pub const SELF_ENERGY: usize = 7;
+    pub const SELF_INDEX: usize = 8;
+    pub const PRESENT_NODE_CONNECTIONS: usize = 9;
+    pub const PERIOD: usize = 10;
+    pub const FORCE: usize = 11;
+    pub const STATIC_INPUT_NAMES: [&'static str; STATIC_INPUTS] = ["0", "1", "2", "-1", "rand",
+        "present node energy", "present node bot count", "self energy", "self index", "present node connections",
+        "period", "force"];
     pub const TOTAL_INPUTS: usize = STATIC_INPUTS + TOTAL_MEMORY +
         //Add inputs for all the node brains
         TOTAL_NODE_INPUTS * super::nodebrain::TOTAL_OUTPUTS +
@@ -37,117 +96,22 @@ pub mod finalbrain {
         TOTAL_BOT_INPUTS * super::botbrain::TOTAL_OUTPUTS;
     // Mate, Node, Energy Rate (as a sigmoid), Signal, Connect Signal, Pull
     pub const STATIC_OUTPUTS: usize = 7;
+    pub const STATIC_OUTPUT_NAMES: [&'static str; STATIC_OUTPUTS] = ["mate", "node", "rate", "signal",
+        "connect signal", "sever", "pull"];
     pub const TOTAL_OUTPUTS: usize = STATIC_OUTPUTS + TOTAL_MEMORY;
     pub const DEFAULT_MUTATE_SIZE: usize = 8;
     pub const DEFAULT_CROSSOVER_POINTS: usize = 1;
     pub const DEFAULT_INSTRUCTIONS: usize = 128;
+    pub const SHAPE: ::brain::BrainShape = ::brain::BrainShape {
+        inputs: TOTAL_INPUTS,
+        outputs: TOTAL_OUTPUTS,
+        instructions: DEFAULT_INSTRUCTIONS,
+        mutate_size: DEFAULT_MUTATE_SIZE,
+        crossover_points: DEFAULT_CROSSOVER_POINTS,
+    };
 }
 
-pub const MAX_ENERGY: i64 = 10000000;
-pub const MATE_ENERGY: i64 = 10000;
-pub const ENERGY_EXCHANGE_MAGNITUDE: i64 = MAX_ENERGY;
-pub const EXISTENCE_COST: i64 = 1;
-const DEFAULT_ENERGY: i64 = 4 * EXISTENCE_COST;
-const MUTATE_PROBABILITY: f64 = 1.0;
-
-#[derive(Clone)]
-pub enum Ins {
-    _NOP,
-    _ADD,
-    _SUB,
-    _MUL,
-    _DIV,
-    _MOD,
-    _GRT,
-    _LES,
-    _EQL,
-    _NEQ,
-    _AND,
-    _OR,
-    _POW,
-    _EXP,
-    _LN,
-    _SIN,
-    _SQT,
-    MAX,
-}
-
-fn processor(ins: &Ins, a: i64, b: i64) -> i64 {
-    match *ins {
-        Ins::_NOP => a,
-        Ins::_ADD => a + b,
-        Ins::_SUB => a - b,
-        Ins::_MUL => a * b,
-        Ins::_DIV => {
-            match a.checked_div(b) {
-                Some(v) => v,
-                None => 0,
-            }
-        }
-        Ins::_MOD => {
-            if b == 0 {
-                0
-            } else {
-                a.wrapping_rem(b)
-            }
-        }
-        Ins::_GRT => {
-            if a > b {
-                1
-            } else {
-                0
-            }
-        }
-        Ins::_LES => {
-            if a < b {
-                1
-            } else {
-                0
-            }
-        }
-        Ins::_EQL => {
-            if a == b {
-                1
-            } else {
-                0
-            }
-        }
-        Ins::_NEQ => {
-            if a == b {
-                1
-            } else {
-                0
-            }
-        }
-        Ins::_AND => {
-            if a != 0 && b != 0 {
-                1
-            } else {
-                0
-            }
-        }
-        Ins::_OR => {
-            if a != 0 || b != 0 {
-                1
-            } else {
-                0
-            }
-        }
-        Ins::_POW => (a as f64).powf(b as f64) as i64,
-        Ins::_EXP => ((a as f64).exp() * b as f64) as i64,
-        Ins::_LN => ((a as f64).ln() * b as f64) as i64,
-        Ins::_SIN => ((a as f64 / b as f64).sin() * b as f64) as i64,
-        Ins::_SQT => ((a as f64 / b as f64).sqrt() * b as f64) as i64,
-        Ins::MAX => unreachable!(),
-    }
-}
-
-fn mutator(ins: &mut Ins, rng: &mut R) {
-    use std::mem;
-    *ins = unsafe { mem::transmute(rng.gen_range::<u8>(0, Ins::MAX as u8)) };
-}
-
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Decision {
     pub mate: i64,
     pub node: i64,
@@ -173,107 +137,139 @@ impl Default for Decision {
     }
 }
 
+/// The serializable form of a bot. `S` is the serializable form of its brains.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BotState<S = MepState> {
+    pub bot_brain: S,
+    pub node_brain: S,
+    pub final_brain: S,
+    pub energy: i64,
+    pub signal: i64,
+    pub connect_signal: i64,
+    pub memory: [i64; finalbrain::TOTAL_MEMORY],
+    pub decision: Decision,
+    pub id: u64,
+    pub born: u64,
+    pub generation: u64,
+    pub parents: Vec<u64>,
+    pub consumed: i64,
+}
+
 #[derive(Clone)]
-pub struct Bot {
-    pub bot_brain: mli::Mep<Ins, R, i64, fn(&mut Ins, &mut R), fn(&Ins, i64, i64) -> i64>,
-    pub node_brain: mli::Mep<Ins, R, i64, fn(&mut Ins, &mut R), fn(&Ins, i64, i64) -> i64>,
-    pub final_brain: mli::Mep<Ins, R, i64, fn(&mut Ins, &mut R), fn(&Ins, i64, i64) -> i64>,
+pub struct Bot<B: Brain = MepBrain> {
+    pub bot_brain: B,
+    pub node_brain: B,
+    pub final_brain: B,
     pub energy: i64,
     pub signal: i64,
     pub connect_signal: i64,
     pub memory: [i64; finalbrain::TOTAL_MEMORY],
     pub decision: Decision,
+    // Unique for the whole run; assigned along with the birth tick by the world's `Lineage` when the bot is born
+    pub id: u64,
+    pub born: u64,
+    // Spawned bots are generation 0 and offspring are one generation after their latest parent
+    pub generation: u64,
+    // Empty for spawned bots, one parent for division and two for mating
+    pub parents: Vec<u64>,
+    // The total energy the bot has taken from nodes over its life
+    pub consumed: i64,
 }
 
-impl Bot {
-    pub fn new(rng: &mut R) -> Self {
-        let bvec = (0..botbrain::DEFAULT_INSTRUCTIONS)
-            .map(|_| {
-                let mut ins = Ins::_NOP;
-                mutator(&mut ins, rng);
-                ins
-            })
-            .collect::<Vec<_>>();
-        let nvec = (0..nodebrain::DEFAULT_INSTRUCTIONS)
-            .map(|_| {
-                let mut ins = Ins::_NOP;
-                mutator(&mut ins, rng);
-                ins
-            })
-            .collect::<Vec<_>>();
-        let fvec = (0..finalbrain::DEFAULT_INSTRUCTIONS)
-            .map(|_| {
-                let mut ins = Ins::_NOP;
-                mutator(&mut ins, rng);
-                ins
-            })
-            .collect::<Vec<_>>();
-        Bot {
-            bot_brain: mli::Mep::new(botbrain::TOTAL_INPUTS,
-                                     botbrain::TOTAL_OUTPUTS,
-                                     botbrain::DEFAULT_MUTATE_SIZE,
-                                     botbrain::DEFAULT_CROSSOVER_POINTS,
-                                     rng,
-                                     bvec.into_iter(),
-                                     mutator,
-                                     processor),
+impl<B: Brain> Bot<B> {
+    pub fn state(&self) -> BotState<B::State> {
+        BotState {
+            bot_brain: self.bot_brain.state(),
+            node_brain: self.node_brain.state(),
+            final_brain: self.final_brain.state(),
+            energy: self.energy,
+            signal: self.signal,
+            connect_signal: self.connect_signal,
+            memory: self.memory,
+            decision: self.decision.clone(),
+            id: self.id,
+            born: self.born,
+            generation: self.generation,
+            parents: self.parents.clone(),
+            consumed: self.consumed,
+        }
+    }
 
-            node_brain: mli::Mep::new(nodebrain::TOTAL_INPUTS,
-                                      nodebrain::TOTAL_OUTPUTS,
-                                      nodebrain::DEFAULT_MUTATE_SIZE,
-                                      nodebrain::DEFAULT_CROSSOVER_POINTS,
-                                      rng,
-                                      nvec.into_iter(),
-                                      mutator,
-                                      processor),
+    pub fn from_state(state: BotState<B::State>) -> Self {
+        Bot {
+            bot_brain: B::from_state(state.bot_brain, &botbrain::SHAPE),
+            node_brain: B::from_state(state.node_brain, &nodebrain::SHAPE),
+            final_brain: B::from_state(state.final_brain, &finalbrain::SHAPE),
+            energy: state.energy,
+            signal: state.signal,
+            connect_signal: state.connect_signal,
+            memory: state.memory,
+            decision: state.decision,
+            id: state.id,
+            born: state.born,
+            generation: state.generation,
+            parents: state.parents,
+            consumed: state.consumed,
+        }
+    }
 
-            final_brain: mli::Mep::new(finalbrain::TOTAL_INPUTS,
-                                       finalbrain::TOTAL_OUTPUTS,
-                                       finalbrain::DEFAULT_MUTATE_SIZE,
-                                       finalbrain::DEFAULT_CROSSOVER_POINTS,
-                                       rng,
-                                       fvec.into_iter(),
-                                       mutator,
-                                       processor),
+    pub fn new(rng: &mut R, opcodes: &OpcodeTable, config: &SimConfig) -> Self {
+        let shapes = [&botbrain::SHAPE, &nodebrain::SHAPE, &finalbrain::SHAPE];
+        let mut brains = B::random_all(&shapes, opcodes, rng).into_iter();
+        Bot {
+            bot_brain: brains.next().unwrap(),
+            node_brain: brains.next().unwrap(),
+            final_brain: brains.next().unwrap(),
 
-            energy: DEFAULT_ENERGY,
+            // Start with enough energy to exist for a few cycles
+            energy: 4 * config.existence_cost,
 
             signal: 0,
             connect_signal: 0,
 
             memory: [0; finalbrain::TOTAL_MEMORY],
             decision: Default::default(),
+
+            id: 0,
+            born: 0,
+            generation: 0,
+            parents: Vec::new(),
+            consumed: 0,
         }
     }
 
-    pub fn mutate(&mut self, rng: &mut R) {
-        use mli::Genetic;
-        if rng.gen_range(0.0, 1.0) < MUTATE_PROBABILITY {
-            self.bot_brain.mutate(rng);
-            self.node_brain.mutate(rng);
-            self.final_brain.mutate(rng);
+    pub fn mutate(&mut self, rng: &mut R, opcodes: &OpcodeTable, config: &SimConfig) {
+        if rng.gen_range(0.0, 1.0) < config.mutate_probability {
+            self.bot_brain.mutate(opcodes, rng);
+            self.node_brain.mutate(opcodes, rng);
+            self.final_brain.mutate(opcodes, rng);
         }
     }
 
-    pub fn mate(&mut self, other: &Self, rng: &mut R) -> Self {
+    pub fn mate(&mut self, other: &Self, rng: &mut R, opcodes: &OpcodeTable, config: &SimConfig) -> Self {
         // Divide energy in half when mating for the mater
         self.energy /= 2;
         let mut b = Bot {
-            bot_brain: mli::Genetic::mate((&self.bot_brain, &other.bot_brain), rng),
-            node_brain: mli::Genetic::mate((&self.node_brain, &other.node_brain), rng),
-            final_brain: mli::Genetic::mate((&self.final_brain, &other.final_brain), rng),
+            bot_brain: self.bot_brain.crossover(&other.bot_brain, rng),
+            node_brain: self.node_brain.crossover(&other.node_brain, rng),
+            final_brain: self.final_brain.crossover(&other.final_brain, rng),
             energy: self.energy,
             signal: self.signal,
             connect_signal: 0,
             memory: self.memory,
             decision: self.decision.clone(),
+            id: 0,
+            born: 0,
+            generation: ::std::cmp::max(self.generation, other.generation) + 1,
+            parents: vec![self.id, other.id],
+            consumed: 0,
         };
         // Perform unit mutations on offspring
-        b.mutate(rng);
+        b.mutate(rng, opcodes, config);
         b
     }
 
-    pub fn divide(&mut self, rng: &mut R) -> Self {
+    pub fn divide(&mut self, rng: &mut R, opcodes: &OpcodeTable, config: &SimConfig) -> Self {
         // Divide energy in half when dividing
         self.energy /= 2;
         let mut b = Bot {
@@ -286,14 +282,19 @@ impl Bot {
             memory: self.memory,
             // Clone the rate of energy consumption in the decision
             decision: self.decision.clone(),
+            id: 0,
+            born: 0,
+            generation: self.generation + 1,
+            parents: vec![self.id],
+            consumed: 0,
         };
         // Perform unit mutations on offspring
-        b.mutate(rng);
+        b.mutate(rng, opcodes, config);
         b
     }
 
-    pub fn cycle(&mut self) {
-        self.energy = self.energy.saturating_sub(EXISTENCE_COST);
+    pub fn cycle(&mut self, config: &SimConfig) {
+        self.energy = self.energy.saturating_sub(config.existence_cost);
         self.signal = self.decision.signal;
         self.connect_signal = self.decision.connect_signal;
     }
@@ -0,0 +1,82 @@
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use analysis::BrainAnalysis;
+use bot::R;
+use ins::{Arithmetic, Ins, OpcodeTable};
+
+/// The size and genetic parameters of one of a bot's brains.
+#[derive(Clone, Copy, Debug)]
+pub struct BrainShape {
+    pub inputs: usize,
+    pub outputs: usize,
+    // The length of a random program
+    pub instructions: usize,
+    pub mutate_size: usize,
+    pub crossover_points: usize,
+}
+
+/// What an operand of a listed instruction reads.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operand {
+    Input(usize),
+    /// The result of an earlier instruction.
+    Result(usize),
+    /// A register and the earlier instruction that last wrote it, if any did. Registers nothing wrote hold 0.
+    Register(usize, Option<usize>),
+}
+
+/// One instruction of a brain in a form that the disassembler and the dataflow graphs can show for any type of brain.
+#[derive(Clone, Debug)]
+pub struct Listing {
+    pub ins: Ins,
+    /// The operands the instruction reads.
+    pub operands: Vec<Operand>,
+    /// The register the instruction writes, for brains with registers.
+    pub dest: Option<usize>,
+    /// The output that ends up with the instruction's result, if any.
+    pub output: Option<usize>,
+}
+
+/// A program that bots think with. Each bot has three brains of the same type.
+pub trait Brain: Clone + Send + Sync + Sized {
+    /// The serializable form of the brain stored in snapshots.
+    type State: Clone + Serialize + DeserializeOwned;
+
+    /// Make a brain with a random program of instructions drawn from `opcodes`.
+    fn random(shape: &BrainShape, opcodes: &OpcodeTable, rng: &mut R) -> Self;
+
+    /// Make a brain of each shape with random programs, as a new bot needs. Implementations can override this to
+    /// draw random numbers in a different order than making each brain in turn.
+    fn random_all(shapes: &[&BrainShape], opcodes: &OpcodeTable, rng: &mut R) -> Vec<Self> {
+        shapes.iter().map(|shape| Self::random(shape, opcodes, rng)).collect()
+    }
+
+    /// Run the program on `inputs` with the arithmetic mode of the world and write every output.
+    fn compute(&self, arithmetic: Arithmetic, inputs: &[i64], outputs: &mut [i64]);
+
+    /// Make small random changes to the program, drawing new instructions from `opcodes`.
+    fn mutate(&mut self, opcodes: &OpcodeTable, rng: &mut R);
+
+    /// Combine this brain with another of the same shape to make the brain of an offspring.
+    fn crossover(&self, other: &Self, rng: &mut R) -> Self;
+
+    fn state(&self) -> Self::State;
+
+    fn from_state(state: Self::State, shape: &BrainShape) -> Self;
+
+    /// The number of instructions in the program.
+    fn len(&self) -> usize;
+
+    /// Which instructions contribute to an output and which inputs they read.
+    fn analyze(&self) -> BrainAnalysis;
+
+    /// Every instruction of the program in order.
+    fn listing(&self) -> Vec<Listing>;
+
+    /// The most instructions a mutation changes.
+    fn mutate_size(&self) -> usize;
+
+    /// The number of points programs are cut at in crossover.
+    fn crossover_points(&self) -> usize;
+}
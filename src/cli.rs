@@ -0,0 +1,228 @@
+use std::env;
+use std::path::PathBuf;
+use std::process;
+
+use evobots::stats::StatsFormat;
+use evobots::svg::Projection;
+
+const USAGE: &'static str = "Usage: evobots [options]
+
+Options:
+    --seed N        Seed the simulation with the 64-bit integer N
+    --ticks N       Stop after the simulation reaches tick N
+    --config FILE   Load simulation parameters from a TOML file
+    --out DIR       Write run output (such as the config used) to DIR
+    --load FILE     Continue the run saved in the snapshot FILE (its seed and config are used)
+    --save FILE     Save a snapshot of the world to FILE when the run ends
+    --svg FILE      Draw the mesh into the SVG image FILE when the run ends
+    --svg-projection PROJECTION
+                    Flatten the mesh onto the image along xy (default), xz or yz, or through the perspective camera
+    --svg-bot-counts
+                    Label every node in the image with the number of bots in it
+    --graphml FILE  Write the node network to FILE as GraphML when the run ends
+    --gexf FILE     Write the node network to FILE as GEXF when the run ends
+    --gexf-every K  Sample the node network every K ticks and make the GEXF file show how it changed over the run
+    --autosave-every K
+                    Save a snapshot into the autosave folder of the output directory every K ticks
+    --autosave-keep M
+                    Keep only the newest M autosaves (default 3)
+    --resume        Continue from the newest autosave in the output directory (its seed and config are used)
+    --stats-every N Write statistics about the world to the output directory every N ticks
+    --stats-format FORMAT
+                    Write statistics as csv (default) or jsonl
+    --frames-every K
+                    Draw the mesh into numbered PNG files in the frames folder of the output directory every K ticks
+    --frame-width N Width of the drawn frames in pixels (default 1280)
+    --frame-height N
+                    Height of the drawn frames in pixels (default 720)
+    --lineage       Log the birth and death of every bot to the output directory
+    --phylogeny     Write the evolutionary tree of the run to the output directory when it ends
+    --prune-extinct Drop lineages with no living descendants from the tree as the run goes
+    --record-digests FILE
+                    Write a digest of the world after every phase of every tick to FILE
+    --verify-digests FILE
+                    Check every tick against the digests in FILE and stop at the first divergence
+    --disassemble FILE
+                    Print the brains of the bots in the snapshot FILE as program listings and exit
+    --bot ID        Only disassemble the bot with this ID
+    --dot FILE      With --disassemble and --bot, write the bot's brains to FILE as a Graphviz dataflow graph instead
+    --hide-unused   Leave instructions that don't contribute to an output out of the dataflow graph
+    --threads N     Run bot brains on N threads (default one per core); results are the same for any N
+    --headless      Run without opening a window
+    --windowed      Run in the interactive viewer (default when built with rendering)
+    --ticks-per-frame K
+                    Advance the simulation K ticks between frames of the viewer (default 1, at most 65536)
+    --no-vsync      Draw frames of the viewer without waiting for the display so the simulation isn't held back
+    --help          Print this message";
+
+/// The options evobots was launched with.
+pub struct Options {
+    pub seed: Option<u64>,
+    pub ticks: Option<u64>,
+    pub config: Option<PathBuf>,
+    pub out: Option<PathBuf>,
+    pub load: Option<PathBuf>,
+    pub save: Option<PathBuf>,
+    pub svg: Option<PathBuf>,
+    pub svg_projection: Projection,
+    pub svg_bot_counts: bool,
+    pub graphml: Option<PathBuf>,
+    pub gexf: Option<PathBuf>,
+    pub gexf_every: Option<u64>,
+    pub autosave_every: Option<u64>,
+    pub autosave_keep: usize,
+    pub resume: bool,
+    pub stats_every: Option<u64>,
+    pub stats_format: StatsFormat,
+    pub frames_every: Option<u64>,
+    pub frame_width: u32,
+    pub frame_height: u32,
+    pub lineage: bool,
+    pub phylogeny: bool,
+    pub prune_extinct: bool,
+    pub record_digests: Option<PathBuf>,
+    pub verify_digests: Option<PathBuf>,
+    pub disassemble: Option<PathBuf>,
+    pub bot: Option<u64>,
+    pub dot: Option<PathBuf>,
+    pub hide_unused: bool,
+    pub threads: Option<usize>,
+    pub headless: bool,
+    pub ticks_per_frame: u64,
+    pub no_vsync: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            seed: None,
+            ticks: None,
+            config: None,
+            out: None,
+            load: None,
+            save: None,
+            svg: None,
+            svg_projection: Projection::Xy,
+            svg_bot_counts: false,
+            graphml: None,
+            gexf: None,
+            gexf_every: None,
+            autosave_every: None,
+            autosave_keep: 3,
+            resume: false,
+            stats_every: None,
+            stats_format: StatsFormat::Csv,
+            frames_every: None,
+            frame_width: 1280,
+            frame_height: 720,
+            lineage: false,
+            phylogeny: false,
+            prune_extinct: false,
+            record_digests: None,
+            verify_digests: None,
+            disassemble: None,
+            bot: None,
+            dot: None,
+            hide_unused: false,
+            threads: None,
+            headless: !cfg!(feature = "render"),
+            ticks_per_frame: 1,
+            no_vsync: false,
+        }
+    }
+}
+
+fn usage() -> ! {
+    println!("{}", USAGE);
+    process::exit(1);
+}
+
+fn fail(message: &str) -> ! {
+    println!("{}\n\n{}", message, USAGE);
+    process::exit(1);
+}
+
+impl Options {
+    /// Parse the process arguments, exiting with a usage message if they are invalid.
+    pub fn from_args() -> Self {
+        let mut options = Options::default();
+        let mut args = env::args().skip(1);
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().unwrap_or_else(|| fail(&format!("{} requires a value", arg)));
+            match &arg[..] {
+                "--seed" => options.seed = Some(parse(&arg, value())),
+                "--ticks" => options.ticks = Some(parse(&arg, value())),
+                "--config" => options.config = Some(PathBuf::from(value())),
+                "--out" => options.out = Some(PathBuf::from(value())),
+                "--load" => options.load = Some(PathBuf::from(value())),
+                "--save" => options.save = Some(PathBuf::from(value())),
+                "--svg" => options.svg = Some(PathBuf::from(value())),
+                "--svg-projection" => options.svg_projection = parse(&arg, value()),
+                "--svg-bot-counts" => options.svg_bot_counts = true,
+                "--graphml" => options.graphml = Some(PathBuf::from(value())),
+                "--gexf" => options.gexf = Some(PathBuf::from(value())),
+                "--gexf-every" => options.gexf_every = Some(parse(&arg, value())),
+                "--autosave-every" => options.autosave_every = Some(parse(&arg, value())),
+                "--autosave-keep" => options.autosave_keep = parse(&arg, value()),
+                "--resume" => options.resume = true,
+                "--stats-every" => options.stats_every = Some(parse(&arg, value())),
+                "--stats-format" => options.stats_format = parse(&arg, value()),
+                "--frames-every" => options.frames_every = Some(parse(&arg, value())),
+                "--frame-width" => options.frame_width = parse(&arg, value()),
+                "--frame-height" => options.frame_height = parse(&arg, value()),
+                "--lineage" => options.lineage = true,
+                "--phylogeny" => options.phylogeny = true,
+                "--prune-extinct" => options.prune_extinct = true,
+                "--record-digests" => options.record_digests = Some(PathBuf::from(value())),
+                "--verify-digests" => options.verify_digests = Some(PathBuf::from(value())),
+                "--disassemble" => options.disassemble = Some(PathBuf::from(value())),
+                "--bot" => options.bot = Some(parse(&arg, value())),
+                "--dot" => options.dot = Some(PathBuf::from(value())),
+                "--hide-unused" => options.hide_unused = true,
+                "--threads" => options.threads = Some(parse(&arg, value())),
+                "--headless" => options.headless = true,
+                "--windowed" => options.headless = false,
+                "--ticks-per-frame" => options.ticks_per_frame = parse(&arg, value()),
+                "--no-vsync" => options.no_vsync = true,
+                "--help" => usage(),
+                _ => fail(&format!("Unknown option {}", arg)),
+            }
+        }
+        let needs_out = options.autosave_every.is_some() || options.resume || options.stats_every.is_some() ||
+                        options.frames_every.is_some() || options.lineage || options.phylogeny;
+        if options.out.is_none() && needs_out {
+            fail("Autosaving, resuming, statistics, frames, lineage logs and phylogenies require an output directory \
+                  (--out)");
+        }
+        if options.gexf_every.is_some() && options.gexf.is_none() {
+            fail("--gexf-every requires a --gexf file to write");
+        }
+        if options.frame_width == 0 || options.frame_height == 0 {
+            fail("Frames must be at least one pixel wide and high");
+        }
+        if options.dot.is_some() && (options.disassemble.is_none() || options.bot.is_none()) {
+            fail("--dot requires a snapshot to --disassemble and the --bot to draw");
+        }
+        if options.ticks_per_frame == 0 {
+            fail("--ticks-per-frame must be at least 1");
+        }
+        if options.autosave_keep == 0 {
+            fail("--autosave-keep must be at least 1");
+        }
+        if options.threads == Some(0) {
+            fail("--threads must be at least 1");
+        }
+        if options.resume && options.load.is_some() {
+            fail("--resume and --load can't be used together");
+        }
+        // A resumed run keeps the seed and config it was started with
+        if options.resume && (options.seed.is_some() || options.config.is_some()) {
+            fail("--resume continues with the seed and config of the autosave, so --seed and --config can't be used");
+        }
+        options
+    }
+}
+
+fn parse<T: ::std::str::FromStr>(arg: &str, value: String) -> T {
+    value.parse().unwrap_or_else(|_| fail(&format!("Invalid value {} for {}", value, arg)))
+}
@@ -0,0 +1,207 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use toml;
+use zoom;
+
+use super::Vec3;
+use ins::{self, Arithmetic, Ins};
+
+/// All of the tuning knobs of the simulation. Every field has a default equal to the value evobots has always used,
+/// so a config file only needs to mention the parameters it changes.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SimConfig {
+    // Contol the size of simulation and the energy production simultaneously
+    pub size_factor: f64,
+    // Half the width of the toroidal space the nodes live in (scaled by size_factor)
+    pub node_space: f64,
+
+    // Magnitude of flinging apart of a node that split
+    pub separation_magnitude: f64,
+    pub separation_delta: f64,
+    // Magnitude of repulsion between all particles
+    pub repulsion_magnitude: f64,
+    // Edge attraction
+    pub attraction_magnitude: f64,
+    // Probability of connecting after node is destroyed
+    pub connect_probability: f64,
+    pub connect_after: f64,
+    // Edges longer than this are broken (scaled by size_factor)
+    pub connect_max_length: f64,
+    // The length within which bots can connect their nodes together by choice
+    pub bot_choice_connect_length: f64,
+    pub bot_pull_magnitude: f64,
+    pub bot_pull_radius: f64,
+    // Accuracy of the repulsion between distant nodes: a group of nodes narrower than this fraction of its distance
+    // repels as a single node (Barnes-Hut), which takes time closer to n log n than n^2 for n nodes. 0 is the exact
+    // repulsion between every pair of nodes
+    pub barnes_hut_theta: f64,
+
+    // The inverse of the rate at which bots spawn
+    pub start_spawning_at: i64,
+    // Energy stops being generated after this many nodes exist
+    pub energy_cutoff_at: usize,
+    pub node_starting_energy: i64,
+    pub new_node_spawns: usize,
+    // Cycle mutation rate; always mutates on division either way
+    pub mutation_rate: f64,
+    // The rate at which a bot will be spawned in empty nodes when the mesh is full
+    pub empty_node_full_mesh_spawn_rate: f64,
+    // Minimum channel magnitude to connect
+    pub connect_signal_min: i64,
+
+    pub sigmoid_decompression: f64,
+    pub force_input_scalar: f64,
+
+    // Energy growth of the whole mesh (scaled by the cube of size_factor)
+    pub energy_ratio_net: f64,
+    pub energy_ratio_single_limit: f64,
+    pub energy_variation: f64,
+    // Nodes split when they reach this much energy
+    pub energy_threshold: i64,
+    // Energy lost by empty nodes once the mesh is full
+    pub energy_full_cost: i64,
+
+    pub edge_food_benefit: f64,
+    pub have_edge_food_benefit: f64,
+    pub have_three_edge_food_benefit: f64,
+    pub edge_diffusion_coefficient: f64,
+
+    pub drag: f64,
+
+    pub max_energy: i64,
+    pub mate_energy: i64,
+    pub energy_exchange_magnitude: i64,
+    pub existence_cost: i64,
+    pub mutate_probability: f64,
+
+    // How brain instructions handle overflow: "wrapping", "saturating" or "checked"
+    pub arithmetic: Arithmetic,
+    // How likely mutation is to produce each instruction, by mnemonic; 0 disables an instruction. Instructions left out
+    // keep their default weight, which is 1 for the original instructions and 0 for the extended ones
+    pub instruction_weights: BTreeMap<String, f64>,
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        SimConfig {
+            size_factor: 1.2,
+            node_space: 300.0,
+
+            separation_magnitude: 0.015,
+            separation_delta: 10.0,
+            repulsion_magnitude: 500.0,
+            attraction_magnitude: 0.003,
+            connect_probability: 0.0,
+            connect_after: 40.0,
+            connect_max_length: 150.0,
+            bot_choice_connect_length: 50000.0,
+            bot_pull_magnitude: 150.0,
+            bot_pull_radius: 200.0,
+            barnes_hut_theta: 0.0,
+
+            start_spawning_at: 50000,
+            energy_cutoff_at: 150,
+            node_starting_energy: 200000,
+            new_node_spawns: 0,
+            mutation_rate: 0.000001,
+            empty_node_full_mesh_spawn_rate: 0.005,
+            connect_signal_min: 16,
+
+            sigmoid_decompression: 4294967296.0,
+            force_input_scalar: 4294967296.0,
+
+            energy_ratio_net: 2.0 * 20.0,
+            energy_ratio_single_limit: 1.0,
+            energy_variation: 0.2,
+            energy_threshold: 5000000,
+            energy_full_cost: 50000,
+
+            edge_food_benefit: 0.0,
+            have_edge_food_benefit: 0.0,
+            have_three_edge_food_benefit: 0.0,
+            edge_diffusion_coefficient: 0.05,
+
+            drag: 0.4,
+
+            max_energy: 10000000,
+            mate_energy: 10000,
+            energy_exchange_magnitude: 10000000,
+            existence_cost: 1,
+            mutate_probability: 1.0,
+
+            arithmetic: Arithmetic::Wrapping,
+            instruction_weights: ins::default_weights()
+                .into_iter()
+                .map(|(i, w)| (i.mnemonic().to_string(), w))
+                .collect(),
+        }
+    }
+}
+
+impl SimConfig {
+    /// Load a config from a TOML file. Parameters missing from the file keep their defaults.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut s = String::new();
+        File::open(path)?.read_to_string(&mut s)?;
+        SimConfig::from_toml(&s)
+    }
+
+    /// Parse a config from TOML, such as that given by `to_toml`. Parameters missing from it keep their defaults.
+    pub fn from_toml(s: &str) -> io::Result<Self> {
+        let mut config: SimConfig = toml::from_str(s).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        // Fill in the weights that were left out so that the config echo shows every weight in use
+        let weights = config.instruction_weights().map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        config.instruction_weights = weights.into_iter().map(|(i, w)| (i.mnemonic().to_string(), w)).collect();
+        Ok(config)
+    }
+
+    /// The weight of every instruction, with defaults for those left out of `instruction_weights`.
+    pub fn instruction_weights(&self) -> Result<Vec<(Ins, f64)>, String> {
+        let mut weights = ins::default_weights();
+        for (name, &w) in &self.instruction_weights {
+            let i = Ins::from_mnemonic(name).ok_or_else(|| format!("unknown instruction {}", name))?;
+            if !(w >= 0.0 && w.is_finite()) {
+                return Err(format!("the weight of {} must be a finite number of at least 0", name));
+            }
+            weights[i as usize].1 = w;
+        }
+        if weights.iter().all(|&(_, w)| w == 0.0) {
+            return Err("at least one instruction must have a positive weight".to_string());
+        }
+        Ok(weights)
+    }
+
+    /// Render the config as TOML so that it can be echoed and later loaded to reproduce a run.
+    pub fn to_toml(&self) -> String {
+        toml::to_string(self).unwrap()
+    }
+
+    pub fn node_space(&self) -> zoom::Box<Vec3> {
+        zoom::Box {
+            origin: Vec3::new(0.0, 0.0, 0.0),
+            offset: Vec3::new(self.node_space * self.size_factor,
+                              self.node_space * self.size_factor,
+                              self.node_space * self.size_factor),
+        }
+    }
+
+    pub fn connect_max_length(&self) -> f64 {
+        self.connect_max_length * self.size_factor
+    }
+
+    pub fn spawn_rate(&self) -> f64 {
+        1.0 / (self.start_spawning_at as f64)
+    }
+
+    pub fn energy_ratio_net(&self) -> f64 {
+        self.energy_ratio_net * self.size_factor * self.size_factor * self.size_factor
+    }
+
+    pub fn sig(&self, v: i64) -> f64 {
+        (1.0 / (1.0 + (v as f64 / self.sigmoid_decompression).exp()) - 0.5)
+    }
+}
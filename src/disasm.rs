@@ -0,0 +1,154 @@
+use std::fmt::Write;
+
+use bot::*;
+use brain::Operand;
+
+/// Which of a bot's three brains a program is, which decides what its inputs and outputs mean.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BrainKind {
+    Node,
+    Bot,
+    Final,
+}
+
+pub const BRAIN_KINDS: [BrainKind; 3] = [BrainKind::Node, BrainKind::Bot, BrainKind::Final];
+
+impl BrainKind {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            BrainKind::Node => "node brain",
+            BrainKind::Bot => "bot brain",
+            BrainKind::Final => "final brain",
+        }
+    }
+
+    pub fn inputs(&self) -> usize {
+        match *self {
+            BrainKind::Node => nodebrain::TOTAL_INPUTS,
+            BrainKind::Bot => botbrain::TOTAL_INPUTS,
+            BrainKind::Final => finalbrain::TOTAL_INPUTS,
+        }
+    }
+
+    pub fn outputs(&self) -> usize {
+        match *self {
+            BrainKind::Node => nodebrain::TOTAL_OUTPUTS,
+            BrainKind::Bot => botbrain::TOTAL_OUTPUTS,
+            BrainKind::Final => finalbrain::TOTAL_OUTPUTS,
+        }
+    }
+
+    /// The brain of this kind in a bot.
+    pub fn of<'a, B: Brain>(&self, bot: &'a Bot<B>) -> &'a B {
+        match *self {
+            BrainKind::Node => &bot.node_brain,
+            BrainKind::Bot => &bot.bot_brain,
+            BrainKind::Final => &bot.final_brain,
+        }
+    }
+
+    /// What the input at index `i` holds.
+    pub fn input_name(&self, i: usize) -> String {
+        let (names, statics): (&[&str], usize) = match *self {
+            BrainKind::Node => (&nodebrain::STATIC_INPUT_NAMES, nodebrain::STATIC_INPUTS),
+            BrainKind::Bot => (&botbrain::STATIC_INPUT_NAMES, botbrain::STATIC_INPUTS),
+            BrainKind::Final => (&finalbrain::STATIC_INPUT_NAMES, finalbrain::STATIC_INPUTS),
+        };
+        if i < statics {
+            return names[i].to_string();
+        }
+        let i = i - statics;
+        if i < finalbrain::TOTAL_MEMORY {
+            return format!("memory[{}]", i);
+        }
+        // Only the final brain has inputs past its memory: the outputs of the top ranked nodes and then bots
+        let i = i - finalbrain::TOTAL_MEMORY;
+        let node_inputs = finalbrain::TOTAL_NODE_INPUTS * nodebrain::TOTAL_OUTPUTS;
+        let (what, slot, field) = if i < node_inputs {
+            ("node", i / nodebrain::TOTAL_OUTPUTS, i % nodebrain::TOTAL_OUTPUTS)
+        } else {
+            let i = i - node_inputs;
+            ("bot", i / botbrain::TOTAL_OUTPUTS, i % botbrain::TOTAL_OUTPUTS)
+        };
+        // The rank isn't passed on; the index of the chosen node or bot takes its place
+        if field == 0 {
+            format!("{} {} index", what, slot)
+        } else {
+            format!("{} {} value {}", what, slot, field - 1)
+        }
+    }
+
+    /// What the output at index `i` is used for.
+    pub fn output_name(&self, i: usize) -> String {
+        match *self {
+            BrainKind::Node => nodebrain::OUTPUT_NAMES[i].to_string(),
+            BrainKind::Bot => botbrain::OUTPUT_NAMES[i].to_string(),
+            BrainKind::Final => {
+                if i < finalbrain::STATIC_OUTPUTS {
+                    finalbrain::STATIC_OUTPUT_NAMES[i].to_string()
+                } else {
+                    format!("memory[{}]", i - finalbrain::STATIC_OUTPUTS)
+                }
+            }
+        }
+    }
+
+    /// Describe an operand of an instruction, which is an input, the result of an earlier instruction or a register.
+    fn operand(&self, operand: Operand) -> String {
+        match operand {
+            Operand::Input(i) => self.input_name(i),
+            Operand::Result(i) => format!("[{}]", i),
+            Operand::Register(r, _) => format!("r{}", r),
+        }
+    }
+}
+
+/// A listing of a brain's program with one instruction per line. Operands are named inputs, the results of earlier
+/// instructions in brackets or registers, and the instructions that produce outputs are marked with the output.
+/// Instructions that don't contribute to any output are marked as unused.
+pub fn disassemble<B: Brain>(brain: &B, kind: BrainKind) -> String {
+    let mut out = String::new();
+    let analysis = brain.analyze();
+    writeln!(out,
+             "{} ({} instructions, {} used, {} of {} inputs read, mutate size {}, crossover points {})",
+             kind.name(),
+             brain.len(),
+             analysis.effective_length(),
+             analysis.inputs_read_count(),
+             kind.inputs(),
+             brain.mutate_size(),
+             brain.crossover_points())
+        .unwrap();
+    for (i, line) in brain.listing().iter().enumerate() {
+        let operands = line.operands.iter().map(|&o| kind.operand(o)).collect::<Vec<_>>();
+        write!(out, "{:5}: ", i).unwrap();
+        if let Some(r) = line.dest {
+            write!(out, "r{} = ", r).unwrap();
+        }
+        write!(out, "{:<4} {}", line.ins.mnemonic(), operands.join(", ")).unwrap();
+        if let Some(o) = line.output {
+            write!(out, " -> {}", kind.output_name(o)).unwrap();
+        } else if !analysis.used[i] {
+            write!(out, " (unused)").unwrap();
+        }
+        writeln!(out, "").unwrap();
+    }
+    out
+}
+
+/// Listings of all three brains of a bot.
+pub fn disassemble_bot<B: Brain>(bot: &Bot<B>) -> String {
+    let mut out = String::new();
+    writeln!(out,
+             "bot {} (generation {}, born at tick {}, energy {})",
+             bot.id,
+             bot.generation,
+             bot.born,
+             bot.energy)
+        .unwrap();
+    for kind in &BRAIN_KINDS {
+        writeln!(out, "").unwrap();
+        out.push_str(&disassemble(kind.of(bot), *kind));
+    }
+    out
+}
@@ -0,0 +1,89 @@
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use bot::*;
+use brain::Operand;
+use disasm::{BrainKind, BRAIN_KINDS};
+
+fn prefix(kind: BrainKind) -> &'static str {
+    match kind {
+        BrainKind::Node => "node",
+        BrainKind::Bot => "bot",
+        BrainKind::Final => "final",
+    }
+}
+
+/// Write the dataflow of one brain as a DOT cluster. Inputs are only drawn if an instruction that is drawn reads them,
+/// and so are the registers read before anything writes them.
+fn write_cluster<B: Brain>(out: &mut String, brain: &B, kind: BrainKind, hide_unused: bool) {
+    let p = prefix(kind);
+    let analysis = brain.analyze();
+    let shown = |i: usize| !hide_unused || analysis.used[i];
+
+    writeln!(out, "    subgraph cluster_{} {{", p).unwrap();
+    writeln!(out, "        label=\"{}\";", kind.name()).unwrap();
+
+    let mut read = BTreeSet::new();
+    let mut unwritten = BTreeSet::new();
+    let mut edges = String::new();
+    let listing = brain.listing();
+    for (i, line) in listing.iter().enumerate() {
+        if !shown(i) {
+            continue;
+        }
+        let style = if analysis.used[i] { "" } else { ", style=dashed" };
+        writeln!(out, "        {}_{} [label=\"{}\"{}];", p, i, line.ins.mnemonic(), style).unwrap();
+        for &operand in &line.operands {
+            match operand {
+                Operand::Input(input) => {
+                    read.insert(input);
+                    writeln!(edges, "        {}_in{} -> {}_{};", p, input, p, i).unwrap();
+                }
+                Operand::Result(j) |
+                Operand::Register(_, Some(j)) => writeln!(edges, "        {}_{} -> {}_{};", p, j, p, i).unwrap(),
+                Operand::Register(r, None) => {
+                    unwritten.insert(r);
+                    writeln!(edges, "        {}_r{} -> {}_{};", p, r, p, i).unwrap();
+                }
+            }
+        }
+    }
+    for i in read {
+        writeln!(out, "        {}_in{} [label=\"{}\", shape=box];", p, i, kind.input_name(i)).unwrap();
+    }
+    for r in unwritten {
+        writeln!(out, "        {}_r{} [label=\"r{} = 0\", shape=box];", p, r, r).unwrap();
+    }
+    for (i, line) in listing.iter().enumerate() {
+        if let Some(o) = line.output {
+            writeln!(out, "        {}_out{} [label=\"{}\", shape=doubleoctagon];", p, o, kind.output_name(o)).unwrap();
+            writeln!(edges, "        {}_{} -> {}_out{};", p, i, p, o).unwrap();
+        }
+    }
+    out.push_str(&edges);
+    writeln!(out, "    }}").unwrap();
+}
+
+/// The dataflow of one brain as a DOT graph.
+pub fn brain_to_dot<B: Brain>(brain: &B, kind: BrainKind, hide_unused: bool) -> String {
+    let mut out = String::new();
+    writeln!(out, "digraph {} {{", prefix(kind)).unwrap();
+    writeln!(out, "    rankdir=LR;").unwrap();
+    write_cluster(&mut out, brain, kind, hide_unused);
+    writeln!(out, "}}").unwrap();
+    out
+}
+
+/// The dataflow of all three brains of a bot as a DOT graph with a cluster for each brain. Instructions that don't
+/// contribute to an output are dashed, or left out entirely if `hide_unused` is set.
+pub fn bot_to_dot<B: Brain>(bot: &Bot<B>, hide_unused: bool) -> String {
+    let mut out = String::new();
+    writeln!(out, "digraph bot_{} {{", bot.id).unwrap();
+    writeln!(out, "    rankdir=LR;").unwrap();
+    writeln!(out, "    label=\"bot {} (generation {})\";", bot.id, bot.generation).unwrap();
+    for kind in &BRAIN_KINDS {
+        write_cluster(&mut out, kind.of(bot), *kind, hide_unused);
+    }
+    writeln!(out, "}}").unwrap();
+    out
+}
@@ -0,0 +1,171 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+
+use png;
+use zoom::Toroid;
+
+use bot::Brain;
+use world::World;
+use super::Vec3;
+
+// The camera looks at the origin from this far along the z axis, where the viewer starts
+const CAMERA_DISTANCE: f64 = 1000.0;
+// The vertical field of view in radians, the same as the viewer's
+const FIELD_OF_VIEW: f64 = 1.0;
+// Things closer to the camera than this aren't drawn
+const NEAR: f64 = 1.0;
+// How bright edges are compared to the nodes at their ends
+const EDGE_INTENSITY: f32 = 0.35;
+
+/// Project a position through the camera. The coordinates are 0 at the center of the view and 1 at the top, and the
+/// scale is how much of the view one unit of space covers there. Positions behind the camera give None.
+pub fn perspective(p: Vec3) -> Option<(f64, f64, f64)> {
+    let depth = CAMERA_DISTANCE - p.z;
+    if depth < NEAR {
+        return None;
+    }
+    let scale = 1.0 / ((FIELD_OF_VIEW / 2.0).tan() * depth);
+    Some((p.x * scale, p.y * scale, scale))
+}
+
+/// An image of the mesh drawn without a display. Colors are added together where things overlap, like the glow of
+/// the viewer, and clamped when the image is saved.
+pub struct Frame {
+    pub width: u32,
+    pub height: u32,
+    pixels: Vec<[f32; 3]>,
+}
+
+impl Frame {
+    pub fn new(width: u32, height: u32) -> Self {
+        Frame {
+            width: width,
+            height: height,
+            pixels: vec![[0.0; 3]; width as usize * height as usize],
+        }
+    }
+
+    /// Draw the nodes and edges of a world, colored by `Node::color` and sized by `Node::radius`. Edges that wrap
+    /// around the space are left out, as the viewer does.
+    pub fn draw<B: Brain>(world: &World<B>, width: u32, height: u32) -> Self {
+        let mut frame = Frame::new(width, height);
+        let config = &world.config;
+        let space = config.node_space();
+        for e in world.deps.edge_indices() {
+            let (a, b) = world.deps.edge_endpoints(e).unwrap();
+            let (a, b) = (&world.deps[a], &world.deps[b]);
+            let rdelta = b.particle.p.position - a.particle.p.position;
+            if rdelta == space.wrap_delta(rdelta) {
+                frame.line(a.particle.p.position, a.color(config), b.particle.p.position, b.color(config));
+            }
+        }
+        for n in world.deps.node_weights() {
+            frame.glow(n.particle.p.position, n.color(config), n.radius() as f64);
+        }
+        frame
+    }
+
+    /// The pixel coordinates of a position and the number of pixels one unit of space covers there, or None if it is
+    /// behind the camera.
+    fn project(&self, p: Vec3) -> Option<(f64, f64, f64)> {
+        let half = self.height as f64 / 2.0;
+        perspective(p).map(|(x, y, scale)| (self.width as f64 / 2.0 + x * half, half - y * half, scale * half))
+    }
+
+    fn add(&mut self, x: i64, y: i64, color: [f32; 4], intensity: f32) {
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+            return;
+        }
+        let pixel = &mut self.pixels[y as usize * self.width as usize + x as usize];
+        for c in 0..3 {
+            pixel[c] += color[c] * color[3] * intensity;
+        }
+    }
+
+    /// A disc that fades out towards its edge.
+    fn glow(&mut self, p: Vec3, color: [f32; 4], radius: f64) {
+        if let Some((x, y, scale)) = self.project(p) {
+            // Even the smallest nodes cover a pixel
+            let r = (radius * scale).max(1.0);
+            let (x0, x1) = (((x - r).floor() as i64).max(0), ((x + r).ceil() as i64).min(self.width as i64 - 1));
+            let (y0, y1) = (((y - r).floor() as i64).max(0), ((y + r).ceil() as i64).min(self.height as i64 - 1));
+            for py in y0..y1 + 1 {
+                for px in x0..x1 + 1 {
+                    let (dx, dy) = (px as f64 + 0.5 - x, py as f64 + 0.5 - y);
+                    let d2 = (dx * dx + dy * dy) / (r * r);
+                    if d2 < 1.0 {
+                        self.add(px, py, color, (1.0 - d2) as f32);
+                    }
+                }
+            }
+        }
+    }
+
+    /// A one pixel wide line that blends from the color of one end to the other.
+    fn line(&mut self, a: Vec3, ca: [f32; 4], b: Vec3, cb: [f32; 4]) {
+        if let (Some((ax, ay, _)), Some((bx, by, _))) = (self.project(a), self.project(b)) {
+            let steps = (bx - ax).abs().max((by - ay).abs()).ceil().max(1.0);
+            // Lines far outside of the frame would take forever to walk
+            if steps > 4.0 * (self.width + self.height) as f64 {
+                return;
+            }
+            let steps = steps as i64;
+            for s in 0..steps + 1 {
+                let t = s as f64 / steps as f64;
+                let mut color = [0.0; 4];
+                for c in 0..4 {
+                    color[c] = ca[c] + (cb[c] - ca[c]) * t as f32;
+                }
+                let (x, y) = (ax + (bx - ax) * t, ay + (by - ay) * t);
+                self.add(x.floor() as i64, y.floor() as i64, color, EDGE_INTENSITY);
+            }
+        }
+    }
+
+    /// Save the frame as an 8-bit RGB PNG.
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let data = self.pixels
+            .iter()
+            .flat_map(|p| p.iter().map(|&c| (c.max(0.0).min(1.0) * 255.0).round() as u8))
+            .collect::<Vec<_>>();
+        let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), self.width, self.height);
+        encoder.set_color(png::ColorType::RGB);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()
+            .and_then(|mut writer| writer.write_image_data(&data))
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+    }
+}
+
+/// Periodically draws the world into a directory of numbered PNG files for making time-lapse videos.
+///
+/// Frames are numbered by tick divided by the interval, so a resumed run continues the numbering where it left off.
+pub struct FrameRecorder {
+    dir: PathBuf,
+    every: u64,
+    width: u32,
+    height: u32,
+}
+
+impl FrameRecorder {
+    pub fn new<P: Into<PathBuf>>(dir: P, every: u64, width: u32, height: u32) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(FrameRecorder {
+            dir: dir,
+            every: every,
+            width: width,
+            height: height,
+        })
+    }
+
+    /// Draw the world if it is on a tick that should be drawn.
+    pub fn tick<B: Brain>(&self, world: &World<B>) -> io::Result<()> {
+        if self.every != 0 && world.period % self.every == 0 {
+            let path = self.dir.join(format!("frame-{:08}.png", world.period / self.every));
+            Frame::draw(world, self.width, self.height).save_png(path)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,447 @@
+use std::cell::Cell;
+use std::ptr;
+
+use rand::Rng;
+
+use bot::R;
+
+/// The instructions brains are made of. Every instruction takes two operands, though some ignore the second.
+///
+/// New instructions must only ever be added at the end, and to the end of `OPCODES`. Genomes are stored by instruction
+/// index, so inserting or reordering instructions would change what every saved genome does.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Ins {
+    _NOP,
+    _ADD,
+    _SUB,
+    _MUL,
+    _DIV,
+    _MOD,
+    _GRT,
+    _LES,
+    _EQL,
+    _NEQ,
+    _AND,
+    _OR,
+    _POW,
+    _EXP,
+    _LN,
+    _SIN,
+    _SQT,
+    // The extended instructions, which are only generated when they are enabled
+    _SEL,
+    _MIN,
+    _MAX,
+    _ABS,
+    _NEG,
+    _SHL,
+    _SHR,
+    _XOR,
+    _BAND,
+    _BOR,
+    _CLP,
+    _SADD,
+    _SSUB,
+    _SMUL,
+}
+
+/// Every instruction in index order.
+pub const OPCODES: [Ins; 31] = [Ins::_NOP, Ins::_ADD, Ins::_SUB, Ins::_MUL, Ins::_DIV, Ins::_MOD, Ins::_GRT, Ins::_LES,
+                                Ins::_EQL, Ins::_NEQ, Ins::_AND, Ins::_OR, Ins::_POW, Ins::_EXP, Ins::_LN, Ins::_SIN,
+                                Ins::_SQT, Ins::_SEL, Ins::_MIN, Ins::_MAX, Ins::_ABS, Ins::_NEG, Ins::_SHL, Ins::_SHR,
+                                Ins::_XOR, Ins::_BAND, Ins::_BOR, Ins::_CLP, Ins::_SADD, Ins::_SSUB, Ins::_SMUL];
+
+/// How instructions handle results that don't fit in an `i64`. The same mode gives the same results in debug and
+/// release builds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Arithmetic {
+    /// Integer results wrap around and float results saturate, which is what release builds always did.
+    Wrapping,
+    /// Results are clamped to the nearest value that fits.
+    Saturating,
+    /// Results that don't fit are replaced with 0, as division by zero always was.
+    Checked,
+}
+
+impl Arithmetic {
+    /// Run `f` with `processor` using this mode on the current thread.
+    pub fn scope<T, F: FnOnce() -> T>(self, f: F) -> T {
+        // Put back the mode of any enclosing scope even if `f` panics
+        struct Restore(Option<Arithmetic>);
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                SCOPED_ARITHMETIC.with(|a| a.set(self.0));
+            }
+        }
+        let _restore = Restore(SCOPED_ARITHMETIC.with(|a| a.replace(Some(self))));
+        f()
+    }
+}
+
+// The value an instruction produces in checked mode when its result doesn't fit
+const CHECKED_FALLBACK: i64 = 0;
+
+/// The number of instructions that existed before the extended instructions were added. They are the instructions
+/// enabled by default.
+pub const ORIGINAL_INSTRUCTIONS: usize = 17;
+
+/// The instructions mutation can produce and how likely each is. Every world has its own, built from its config.
+#[derive(Clone, Debug)]
+pub struct OpcodeTable {
+    // Only instructions with a positive weight, in index order
+    entries: Vec<(Ins, f64)>,
+    total: f64,
+    // Whether every entry has the same weight
+    uniform: bool,
+}
+
+impl OpcodeTable {
+    /// A table of the instructions with a positive weight. Instructions left out or with a weight of 0 are never
+    /// produced, but instructions already in genomes keep working either way.
+    pub fn new(weights: &[(Ins, f64)]) -> Self {
+        let mut entries = weights.iter().cloned().filter(|&(_, w)| w > 0.0).collect::<Vec<_>>();
+        entries.sort_by_key(|&(i, _)| i as usize);
+        assert!(!entries.is_empty(), "at least one instruction must have a positive weight");
+        OpcodeTable {
+            total: entries.iter().map(|&(_, w)| w).sum(),
+            uniform: entries.iter().all(|&(_, w)| w == entries[0].1),
+            entries: entries,
+        }
+    }
+
+    /// The instructions the table can produce, in index order.
+    pub fn enabled(&self) -> Vec<Ins> {
+        self.entries.iter().map(|&(i, _)| i).collect()
+    }
+
+    /// Replace an instruction with one drawn from the table.
+    pub fn mutate(&self, ins: &mut Ins, rng: &mut R) {
+        // Equal weights draw an index directly rather than walking the table
+        if self.uniform {
+            *ins = self.entries[rng.gen_range::<u8>(0, self.entries.len() as u8) as usize].0;
+            return;
+        }
+        let mut choice = rng.gen_range(0.0, self.total);
+        for &(i, w) in &self.entries {
+            if choice < w {
+                *ins = i;
+                return;
+            }
+            choice -= w;
+        }
+        // Rounding can leave the choice just past the last entry
+        *ins = self.entries.last().unwrap().0;
+    }
+
+    /// Run `f` with `mutator` drawing from this table on the current thread.
+    pub fn scope<T, F: FnOnce() -> T>(&self, f: F) -> T {
+        // Put back the table of any enclosing scope even if `f` panics
+        struct Restore(*const OpcodeTable);
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                SCOPED_TABLE.with(|t| t.set(self.0));
+            }
+        }
+        let _restore = Restore(SCOPED_TABLE.with(|t| t.replace(self)));
+        f()
+    }
+}
+
+impl Default for OpcodeTable {
+    fn default() -> Self {
+        OpcodeTable::new(&default_weights())
+    }
+}
+
+thread_local! {
+    // The table of the innermost `OpcodeTable::scope` on this thread
+    static SCOPED_TABLE: Cell<*const OpcodeTable> = Cell::new(ptr::null());
+    // The mode of the innermost `Arithmetic::scope` on this thread
+    static SCOPED_ARITHMETIC: Cell<Option<Arithmetic>> = Cell::new(None);
+}
+
+impl Ins {
+    /// The instruction with discriminant `index`, if there is one.
+    pub fn from_index(index: usize) -> Option<Ins> {
+        OPCODES.get(index).cloned()
+    }
+
+    pub fn from_mnemonic(name: &str) -> Option<Ins> {
+        OPCODES.iter().cloned().find(|i| i.mnemonic().eq_ignore_ascii_case(name))
+    }
+
+    /// The name of the instruction in disassembly and in the config.
+    pub fn mnemonic(&self) -> &'static str {
+        match *self {
+            Ins::_NOP => "NOP",
+            Ins::_ADD => "ADD",
+            Ins::_SUB => "SUB",
+            Ins::_MUL => "MUL",
+            Ins::_DIV => "DIV",
+            Ins::_MOD => "MOD",
+            Ins::_GRT => "GRT",
+            Ins::_LES => "LES",
+            Ins::_EQL => "EQL",
+            Ins::_NEQ => "NEQ",
+            Ins::_AND => "AND",
+            Ins::_OR => "OR",
+            Ins::_POW => "POW",
+            Ins::_EXP => "EXP",
+            Ins::_LN => "LN",
+            Ins::_SIN => "SIN",
+            Ins::_SQT => "SQT",
+            Ins::_SEL => "SEL",
+            Ins::_MIN => "MIN",
+            Ins::_MAX => "MAX",
+            Ins::_ABS => "ABS",
+            Ins::_NEG => "NEG",
+            Ins::_SHL => "SHL",
+            Ins::_SHR => "SHR",
+            Ins::_XOR => "XOR",
+            Ins::_BAND => "BAND",
+            Ins::_BOR => "BOR",
+            Ins::_CLP => "CLP",
+            Ins::_SADD => "SADD",
+            Ins::_SSUB => "SSUB",
+            Ins::_SMUL => "SMUL",
+        }
+    }
+
+    /// How many of the two operands the instruction reads.
+    pub fn operands(&self) -> usize {
+        match *self {
+            Ins::_NOP | Ins::_ABS | Ins::_NEG => 1,
+            _ => 2,
+        }
+    }
+}
+
+/// The weight of every instruction by default: the original instructions are equally likely and the extended ones
+/// are disabled.
+pub fn default_weights() -> Vec<(Ins, f64)> {
+    OPCODES.iter()
+        .map(|&i| (i, if (i as usize) < ORIGINAL_INSTRUCTIONS { 1.0 } else { 0.0 }))
+        .collect()
+}
+
+fn integer(mode: Arithmetic,
+           wrapping: fn(i64, i64) -> i64,
+           saturating: fn(i64, i64) -> i64,
+           checked: fn(i64, i64) -> Option<i64>,
+           a: i64,
+           b: i64)
+           -> i64 {
+    match mode {
+        Arithmetic::Wrapping => wrapping(a, b),
+        Arithmetic::Saturating => saturating(a, b),
+        Arithmetic::Checked => checked(a, b).unwrap_or(CHECKED_FALLBACK),
+    }
+}
+
+/// Convert the result of a float instruction. NaN is always 0. Out of range values saturate unless checking, which
+/// doesn't rely on how the compiler version in use casts them.
+fn float(mode: Arithmetic, f: f64) -> i64 {
+    // The smallest float above i64::MAX
+    const LIMIT: f64 = 9223372036854775808.0;
+    if f.is_nan() {
+        0
+    } else if f >= LIMIT {
+        if mode == Arithmetic::Checked { CHECKED_FALLBACK } else { i64::max_value() }
+    } else if f < -LIMIT {
+        if mode == Arithmetic::Checked { CHECKED_FALLBACK } else { i64::min_value() }
+    } else {
+        f as i64
+    }
+}
+
+/// The processor for mli, which can only be given a plain function pointer. It uses the mode of the enclosing
+/// `Arithmetic::scope` and panics outside of one.
+pub fn processor(ins: &Ins, a: i64, b: i64) -> i64 {
+    let mode = SCOPED_ARITHMETIC.with(|a| a.get())
+        .expect("instructions can only be processed inside Arithmetic::scope");
+    evaluate(mode, ins, a, b)
+}
+
+/// Run one instruction.
+#[inline]
+pub fn evaluate(mode: Arithmetic, ins: &Ins, a: i64, b: i64) -> i64 {
+    match *ins {
+        Ins::_NOP => a,
+        Ins::_ADD => integer(mode, i64::wrapping_add, i64::saturating_add, i64::checked_add, a, b),
+        Ins::_SUB => integer(mode, i64::wrapping_sub, i64::saturating_sub, i64::checked_sub, a, b),
+        Ins::_MUL => integer(mode, i64::wrapping_mul, i64::saturating_mul, i64::checked_mul, a, b),
+        Ins::_DIV => {
+            match a.checked_div(b) {
+                Some(v) => v,
+                None => 0,
+            }
+        }
+        Ins::_MOD => {
+            if b == 0 {
+                0
+            } else {
+                a.wrapping_rem(b)
+            }
+        }
+        Ins::_GRT => {
+            if a > b {
+                1
+            } else {
+                0
+            }
+        }
+        Ins::_LES => {
+            if a < b {
+                1
+            } else {
+                0
+            }
+        }
+        Ins::_EQL => {
+            if a == b {
+                1
+            } else {
+                0
+            }
+        }
+        Ins::_NEQ => {
+            if a == b {
+                1
+            } else {
+                0
+            }
+        }
+        Ins::_AND => {
+            if a != 0 && b != 0 {
+                1
+            } else {
+                0
+            }
+        }
+        Ins::_OR => {
+            if a != 0 || b != 0 {
+                1
+            } else {
+                0
+            }
+        }
+        Ins::_POW => float(mode, (a as f64).powf(b as f64)),
+        Ins::_EXP => float(mode, (a as f64).exp() * b as f64),
+        Ins::_LN => float(mode, (a as f64).ln() * b as f64),
+        Ins::_SIN => float(mode, (a as f64 / b as f64).sin() * b as f64),
+        Ins::_SQT => float(mode, (a as f64 / b as f64).sqrt() * b as f64),
+        // Select a if it is positive and b otherwise
+        Ins::_SEL => {
+            if a > 0 {
+                a
+            } else {
+                b
+            }
+        }
+        Ins::_MIN => ::std::cmp::min(a, b),
+        Ins::_MAX => ::std::cmp::max(a, b),
+        Ins::_ABS => {
+            match mode {
+                Arithmetic::Wrapping => a.wrapping_abs(),
+                Arithmetic::Saturating => a.checked_abs().unwrap_or(i64::max_value()),
+                Arithmetic::Checked => a.checked_abs().unwrap_or(CHECKED_FALLBACK),
+            }
+        }
+        Ins::_NEG => integer(mode, i64::wrapping_sub, i64::saturating_sub, i64::checked_sub, 0, a),
+        // Shift amounts wrap around at 64
+        Ins::_SHL => a.wrapping_shl(b as u32),
+        Ins::_SHR => a.wrapping_shr(b as u32),
+        Ins::_XOR => a ^ b,
+        Ins::_BAND => a & b,
+        Ins::_BOR => a | b,
+        // Clamp a to between -|b| and |b|
+        Ins::_CLP => {
+            let limit = b.checked_abs().unwrap_or(i64::max_value());
+            ::std::cmp::max(-limit, ::std::cmp::min(a, limit))
+        }
+        Ins::_SADD => a.saturating_add(b),
+        Ins::_SSUB => a.saturating_sub(b),
+        Ins::_SMUL => a.saturating_mul(b),
+    }
+}
+
+/// The mutator for mli, which can only be given a plain function pointer. It draws from the table of the enclosing
+/// `OpcodeTable::scope` and panics outside of one.
+pub fn mutator(ins: &mut Ins, rng: &mut R) {
+    let table = SCOPED_TABLE.with(|t| t.get());
+    assert!(!table.is_null(), "instructions can only be mutated inside OpcodeTable::scope");
+    // The scope that set the pointer borrows the table until it ends
+    unsafe { &*table }.mutate(ins, rng)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{evaluate, Arithmetic, Ins, CHECKED_FALLBACK};
+
+    const MAX: i64 = ::std::i64::MAX;
+    const MIN: i64 = ::std::i64::MIN;
+    const MODES: [Arithmetic; 3] = [Arithmetic::Wrapping, Arithmetic::Saturating, Arithmetic::Checked];
+
+    // These would panic in a debug build if the processor used plain operators
+    fn overflowing(mode: Arithmetic) -> Vec<i64> {
+        vec![evaluate(mode, &Ins::_ADD, MAX, 1),
+             evaluate(mode, &Ins::_SUB, MIN, 1),
+             evaluate(mode, &Ins::_MUL, MAX, 2),
+             evaluate(mode, &Ins::_MUL, MIN, -1),
+             evaluate(mode, &Ins::_ABS, MIN, 0),
+             evaluate(mode, &Ins::_NEG, MIN, 0)]
+    }
+
+    #[test]
+    fn wrapping_wraps_integers() {
+        assert_eq!(overflowing(Arithmetic::Wrapping), vec![MIN, MAX, -2, MIN, MIN, MIN]);
+    }
+
+    #[test]
+    fn saturating_clamps_integers() {
+        assert_eq!(overflowing(Arithmetic::Saturating), vec![MAX, MIN, MAX, MAX, MAX, MAX]);
+    }
+
+    #[test]
+    fn checked_replaces_integers() {
+        assert_eq!(overflowing(Arithmetic::Checked), vec![CHECKED_FALLBACK; 6]);
+    }
+
+    #[test]
+    fn floats_out_of_range() {
+        for &mode in &MODES {
+            let (high, low) = if mode == Arithmetic::Checked {
+                (CHECKED_FALLBACK, CHECKED_FALLBACK)
+            } else {
+                (MAX, MIN)
+            };
+            assert_eq!(evaluate(mode, &Ins::_POW, 10, 30), high);
+            assert_eq!(evaluate(mode, &Ins::_POW, -10, 31), low);
+            assert_eq!(evaluate(mode, &Ins::_EXP, 1000, 1), high);
+            // NaN is 0 in every mode
+            assert_eq!(evaluate(mode, &Ins::_SQT, -4, 1), 0);
+        }
+    }
+
+    #[test]
+    fn every_mode_agrees_in_range() {
+        for &mode in &MODES {
+            assert_eq!(evaluate(mode, &Ins::_ADD, 2, 3), 5);
+            assert_eq!(evaluate(mode, &Ins::_SUB, 2, 3), -1);
+            assert_eq!(evaluate(mode, &Ins::_MUL, -4, 3), -12);
+            assert_eq!(evaluate(mode, &Ins::_ABS, -7, 0), 7);
+            assert_eq!(evaluate(mode, &Ins::_POW, 2, 10), 1024);
+        }
+    }
+
+    #[test]
+    fn division_never_traps() {
+        for &mode in &MODES {
+            assert_eq!(evaluate(mode, &Ins::_DIV, 1, 0), 0);
+            assert_eq!(evaluate(mode, &Ins::_DIV, MIN, -1), 0);
+            assert_eq!(evaluate(mode, &Ins::_MOD, 1, 0), 0);
+            assert_eq!(evaluate(mode, &Ins::_MOD, MIN, -1), 0);
+        }
+    }
+}
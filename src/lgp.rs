@@ -0,0 +1,193 @@
+use rand::Rng;
+
+use analysis::BrainAnalysis;
+use brain::{Brain, BrainShape, Listing, Operand};
+use bot::R;
+use ins::{evaluate, Arithmetic, Ins, OpcodeTable};
+
+// Registers beyond those holding the outputs that programs can use for intermediate results
+const SCRATCH_REGISTERS: usize = 4;
+
+/// One instruction of a linear genetic program: `dest = ins(a, b)`.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct LgpInstruction {
+    pub ins: Ins,
+    /// The register written.
+    pub dest: usize,
+    /// Operands below the number of registers read registers and the rest read inputs, offset by the number of
+    /// registers.
+    pub a: usize,
+    pub b: usize,
+}
+
+/// A linear genetic program with registers. Unlike a `MepBrain`, instructions can overwrite earlier results, so the
+/// same register can be reused many times along the program.
+///
+/// All registers start at 0 and the outputs are the first registers once the program has run.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct LgpBrain {
+    pub program: Vec<LgpInstruction>,
+    pub registers: usize,
+    pub inputs: usize,
+    pub outputs: usize,
+    pub mutate_size: usize,
+    pub crossover_points: usize,
+}
+
+impl LgpBrain {
+    fn random_instruction(&self, opcodes: &OpcodeTable, rng: &mut R) -> LgpInstruction {
+        let mut ins = Ins::_NOP;
+        opcodes.mutate(&mut ins, rng);
+        let sources = self.registers + self.inputs;
+        LgpInstruction {
+            ins: ins,
+            dest: rng.gen_range(0, self.registers),
+            a: rng.gen_range(0, sources),
+            b: rng.gen_range(0, sources),
+        }
+    }
+}
+
+impl Brain for LgpBrain {
+    type State = LgpBrain;
+
+    fn random(shape: &BrainShape, opcodes: &OpcodeTable, rng: &mut R) -> Self {
+        let mut brain = LgpBrain {
+            program: Vec::with_capacity(shape.instructions),
+            registers: shape.outputs + SCRATCH_REGISTERS,
+            inputs: shape.inputs,
+            outputs: shape.outputs,
+            mutate_size: shape.mutate_size,
+            crossover_points: shape.crossover_points,
+        };
+        for _ in 0..shape.instructions {
+            let i = brain.random_instruction(opcodes, rng);
+            brain.program.push(i);
+        }
+        brain
+    }
+
+    fn compute(&self, arithmetic: Arithmetic, inputs: &[i64], outputs: &mut [i64]) {
+        let mut registers = vec![0; self.registers];
+        for i in &self.program {
+            let read = |o: usize| if o < self.registers { registers[o] } else { inputs[o - self.registers] };
+            let v = evaluate(arithmetic, &i.ins, read(i.a), read(i.b));
+            registers[i.dest] = v;
+        }
+        for (o, &r) in outputs.iter_mut().zip(registers.iter()) {
+            *o = r;
+        }
+    }
+
+    /// Replace one part of up to `mutate_size` random instructions.
+    fn mutate(&mut self, opcodes: &OpcodeTable, rng: &mut R) {
+        if self.program.is_empty() {
+            return;
+        }
+        let times = rng.gen_range(0, self.mutate_size + 1);
+        for _ in 0..times {
+            let ix = rng.gen_range(0, self.program.len());
+            let replacement = self.random_instruction(opcodes, rng);
+            let i = &mut self.program[ix];
+            match rng.gen_range(0, 4) {
+                0 => i.ins = replacement.ins,
+                1 => i.dest = replacement.dest,
+                2 => i.a = replacement.a,
+                _ => i.b = replacement.b,
+            }
+        }
+    }
+
+    /// Alternate between the programs of the parents at `crossover_points` random points.
+    fn crossover(&self, other: &Self, rng: &mut R) -> Self {
+        let len = ::std::cmp::min(self.program.len(), other.program.len());
+        let mut points = (0..self.crossover_points).map(|_| rng.gen_range(0, len + 1)).collect::<Vec<_>>();
+        points.sort();
+        let mut child = self.clone();
+        let mut from_other = false;
+        let mut start = 0;
+        for &end in points.iter().chain(::std::iter::once(&len)) {
+            if from_other {
+                child.program[start..end].copy_from_slice(&other.program[start..end]);
+            }
+            from_other = !from_other;
+            start = end;
+        }
+        child
+    }
+
+    fn state(&self) -> LgpBrain {
+        self.clone()
+    }
+
+    fn from_state(state: LgpBrain, _: &BrainShape) -> Self {
+        state
+    }
+
+    fn len(&self) -> usize {
+        self.program.len()
+    }
+
+    // An instruction is used if a later used instruction or an output reads the register it writes before anything
+    // overwrites it, which is found by walking the program backwards
+    fn analyze(&self) -> BrainAnalysis {
+        let mut analysis = BrainAnalysis {
+            used: vec![false; self.program.len()],
+            inputs_read: vec![false; self.inputs],
+        };
+        let mut live = (0..self.registers).map(|r| r < self.outputs).collect::<Vec<_>>();
+        for (i, instruction) in self.program.iter().enumerate().rev() {
+            if !live[instruction.dest] {
+                continue;
+            }
+            analysis.used[i] = true;
+            live[instruction.dest] = false;
+            for &o in [instruction.a, instruction.b].iter().take(instruction.ins.operands()) {
+                if o < self.registers {
+                    live[o] = true;
+                } else {
+                    analysis.inputs_read[o - self.registers] = true;
+                }
+            }
+        }
+        analysis
+    }
+
+    fn listing(&self) -> Vec<Listing> {
+        let mut written = vec![None; self.registers];
+        let mut listing = Vec::with_capacity(self.program.len());
+        for (i, instruction) in self.program.iter().enumerate() {
+            let operands = [instruction.a, instruction.b]
+                .iter()
+                .take(instruction.ins.operands())
+                .map(|&o| if o < self.registers {
+                    Operand::Register(o, written[o])
+                } else {
+                    Operand::Input(o - self.registers)
+                })
+                .collect();
+            listing.push(Listing {
+                ins: instruction.ins,
+                operands: operands,
+                dest: Some(instruction.dest),
+                output: None,
+            });
+            written[instruction.dest] = Some(i);
+        }
+        // The outputs are whatever the last instructions to write their registers left there
+        for (o, w) in written.into_iter().take(self.outputs).enumerate() {
+            if let Some(i) = w {
+                listing[i].output = Some(o);
+            }
+        }
+        listing
+    }
+
+    fn mutate_size(&self) -> usize {
+        self.mutate_size
+    }
+
+    fn crossover_points(&self) -> usize {
+        self.crossover_points
+    }
+}
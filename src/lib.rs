@@ -0,0 +1,64 @@
+extern crate petgraph;
+extern crate num;
+extern crate nalgebra as na;
+extern crate zoom;
+extern crate rand;
+extern crate itertools;
+extern crate mli;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate toml;
+extern crate bincode;
+extern crate serde_json;
+extern crate rayon;
+extern crate png;
+
+pub type Vec3 = na::Vec3<f64>;
+
+// Seed
+pub const SEED: [u64; 4] = [234, 1, 72, 5];
+
+/// Expand a single integer seed into a seed for the Isaac64 generator using SplitMix64 so that nearby seeds still
+/// produce unrelated worlds.
+pub fn expand_seed(seed: u64) -> [u64; 4] {
+    let mut state = seed;
+    let mut expanded = [0; 4];
+    for s in expanded.iter_mut() {
+        state = state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        *s = z ^ (z >> 31);
+    }
+    expanded
+}
+
+pub mod config;
+pub mod rng;
+pub mod ins;
+pub mod brain;
+pub mod mep;
+pub mod tape;
+pub mod lgp;
+pub mod bot;
+pub mod node;
+pub mod rank;
+pub mod space;
+pub mod world;
+pub mod snapshot;
+pub mod autosave;
+pub mod replay;
+pub mod stats;
+pub mod lineage;
+pub mod phylogeny;
+pub mod disasm;
+pub mod analysis;
+pub mod metrics;
+pub mod dot;
+pub mod frames;
+pub mod svg;
+pub mod network;
+
+pub use config::SimConfig;
+pub use world::World;
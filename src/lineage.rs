@@ -0,0 +1,197 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::vec;
+
+use serde_json;
+
+use bot::{Bot, Brain};
+
+/// Something that happened in the life of a bot.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "lowercase")]
+pub enum Event {
+    /// A bot was spawned (no parents), divided from one parent or mated from two.
+    Birth {
+        tick: u64,
+        id: u64,
+        generation: u64,
+        parents: Vec<u64>,
+    },
+    /// A bot ran out of energy or was in a node when it was obliterated. `energy` is the total it took from nodes
+    /// over its life.
+    Death {
+        tick: u64,
+        id: u64,
+        age: u64,
+        energy: i64,
+    },
+}
+
+impl Event {
+    /// The tick the event happened on.
+    pub fn tick(&self) -> u64 {
+        match *self {
+            Event::Birth { tick, .. } | Event::Death { tick, .. } => tick,
+        }
+    }
+}
+
+/// Hands out bot IDs and keeps the birth and death events that haven't been collected yet.
+pub struct Lineage {
+    next_id: u64,
+    recording: bool,
+    events: Vec<Event>,
+}
+
+impl Lineage {
+    pub fn new() -> Self {
+        Lineage::resume(0)
+    }
+
+    /// Continue handing out IDs from `next_id`.
+    pub fn resume(next_id: u64) -> Self {
+        Lineage {
+            next_id: next_id,
+            recording: false,
+            events: Vec::new(),
+        }
+    }
+
+    /// The ID the next bot born will get.
+    pub fn next_id(&self) -> u64 {
+        self.next_id
+    }
+
+    /// Events are only kept while recording so that they don't pile up when nobody is collecting them.
+    pub fn set_recording(&mut self, recording: bool) {
+        self.recording = recording;
+    }
+
+    /// Give a newborn bot its ID and birth tick. Its generation and parents must already be set.
+    pub fn birth<B: Brain>(&mut self, bot: &mut Bot<B>, tick: u64) {
+        bot.id = self.next_id;
+        bot.born = tick;
+        self.next_id += 1;
+        if self.recording {
+            self.events.push(Event::Birth {
+                tick: tick,
+                id: bot.id,
+                generation: bot.generation,
+                parents: bot.parents.clone(),
+            });
+        }
+    }
+
+    pub fn death<B: Brain>(&mut self, bot: &Bot<B>, tick: u64) {
+        if self.recording {
+            self.events.push(Event::Death {
+                tick: tick,
+                id: bot.id,
+                age: tick - bot.born,
+                energy: bot.consumed,
+            });
+        }
+    }
+
+    /// Take all of the events since the last time they were drained, oldest first.
+    pub fn drain_events(&mut self) -> vec::Drain<Event> {
+        self.events.drain(..)
+    }
+}
+
+/// Writes lineage events to a file as JSON Lines.
+pub struct LineageLog {
+    writer: BufWriter<File>,
+}
+
+impl LineageLog {
+    /// Start a new log at `path`, replacing any log already there.
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(LineageLog { writer: BufWriter::new(File::create(path)?) })
+    }
+
+    /// Continue the log at `path` of a run resumed at tick `period`. Events the run logged from that tick on are
+    /// dropped, since the resumed run will happen again and hand the same bot IDs out to different bots.
+    pub fn resume<P: AsRef<Path>>(path: P, period: u64) -> io::Result<Self> {
+        let path = path.as_ref();
+        let mut kept = Vec::new();
+        if path.exists() {
+            for line in BufReader::new(File::open(path)?).lines() {
+                let line = line?;
+                // A line cut short by a crash doesn't parse and is dropped with the rest
+                match serde_json::from_str::<Event>(&line) {
+                    Ok(ref e) if e.tick() < period => kept.push(line),
+                    _ => {}
+                }
+            }
+        }
+        let mut writer = BufWriter::new(File::create(path)?);
+        for line in &kept {
+            writeln!(writer, "{}", line)?;
+        }
+        writer.flush()?;
+        Ok(LineageLog { writer: writer })
+    }
+
+    pub fn write<I: IntoIterator<Item = Event>>(&mut self, events: I) -> io::Result<()> {
+        for event in events {
+            serde_json::to_writer(&mut self.writer, &event)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            writeln!(self.writer, "")?;
+        }
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs::{self, OpenOptions};
+    use std::io::Write;
+    use std::process;
+
+    use serde_json;
+
+    use super::{Event, LineageLog};
+
+    fn birth(tick: u64, id: u64) -> Event {
+        Event::Birth {
+            tick: tick,
+            id: id,
+            generation: 0,
+            parents: Vec::new(),
+        }
+    }
+
+    fn death(tick: u64, id: u64) -> Event {
+        Event::Death {
+            tick: tick,
+            id: id,
+            age: 0,
+            energy: 0,
+        }
+    }
+
+    #[test]
+    fn resume_drops_events_from_the_resumed_tick_on() {
+        let path = env::temp_dir().join(format!("evobots-lineage-{}.jsonl", process::id()));
+        LineageLog::create(&path).unwrap().write(vec![birth(3, 0), death(4, 0), birth(5, 1), death(7, 1)]).unwrap();
+        // The run crashed in the middle of writing an event
+        write!(OpenOptions::new().append(true).open(&path).unwrap(), "{{\"event\":\"birth\",\"tick\":8").unwrap();
+
+        LineageLog::resume(&path, 5).unwrap().write(vec![birth(5, 2)]).unwrap();
+        let events = fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str::<Event>(l).unwrap())
+            .collect::<Vec<_>>();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(events.iter().map(|e| e.tick()).collect::<Vec<_>>(), vec![3, 4, 5]);
+        match events[2] {
+            Event::Birth { id, .. } => assert_eq!(id, 2),
+            _ => panic!("expected the birth written after resuming"),
+        }
+    }
+}
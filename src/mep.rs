@@ -0,0 +1,181 @@
+use mli;
+
+use analysis::BrainAnalysis;
+use brain::{Brain, BrainShape, Listing, Operand};
+use bot::R;
+use ins::{processor, mutator, Arithmetic, Ins, OpcodeTable};
+use tape::Tape;
+
+pub type Mep = mli::Mep<Ins, R, i64, fn(&mut Ins, &mut R), fn(&Ins, i64, i64) -> i64>;
+
+/// A multi expression program. Every instruction reads inputs or the results of earlier instructions, and the outputs
+/// are the results of the last instructions.
+///
+/// This is the brain evobots has always used. The program is compiled into a `Tape` for evaluation, which is rebuilt
+/// whenever the program changes.
+#[derive(Clone)]
+pub struct MepBrain {
+    mep: Mep,
+    tape: Tape,
+    inputs: usize,
+    outputs: usize,
+}
+
+/// The serializable form of a `MepBrain`: its program and genetic parameters.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct MepState {
+    pub program: Vec<(Ins, usize, usize)>,
+    pub unit_mutate_size: usize,
+    pub crossover_points: usize,
+}
+
+impl MepBrain {
+    /// The instruction and the two operand indices of every instruction. Operands below the number of inputs are
+    /// inputs and the rest are the results of earlier instructions, offset by the number of inputs.
+    pub fn program(&self) -> &[(Ins, usize, usize)] {
+        &self.mep.program
+    }
+
+    /// The compiled program that `compute` runs.
+    pub fn tape(&self) -> &Tape {
+        &self.tape
+    }
+
+    /// Compute the outputs by interpreting the program with mli rather than running the tape. The results are the
+    /// same, only slower.
+    pub fn interpret(&self, arithmetic: Arithmetic, inputs: &[i64], outputs: &mut [i64]) {
+        use mli::SISO;
+        // mli runs instructions with `processor`, which uses the mode of the scope it is called in
+        arithmetic.scope(|| {
+            for (o, v) in outputs.iter_mut().zip(self.mep.compute(inputs)) {
+                *o = v;
+            }
+        });
+    }
+
+    fn from_mep(mep: Mep, inputs: usize, outputs: usize) -> Self {
+        MepBrain {
+            tape: Tape::compile(&mep.program, inputs, outputs),
+            mep: mep,
+            inputs: inputs,
+            outputs: outputs,
+        }
+    }
+
+    fn random_program(shape: &BrainShape, opcodes: &OpcodeTable, rng: &mut R) -> Vec<Ins> {
+        (0..shape.instructions)
+            .map(|_| {
+                let mut ins = Ins::_NOP;
+                opcodes.mutate(&mut ins, rng);
+                ins
+            })
+            .collect()
+    }
+
+    fn build<I>(shape: &BrainShape, mutate_size: usize, crossover_points: usize, rng: &mut R, program: I) -> Self
+        where I: Iterator<Item = Ins>
+    {
+        MepBrain::from_mep(mli::Mep::new(shape.inputs,
+                                         shape.outputs,
+                                         mutate_size,
+                                         crossover_points,
+                                         rng,
+                                         program,
+                                         mutator,
+                                         processor),
+                           shape.inputs,
+                           shape.outputs)
+    }
+}
+
+impl Brain for MepBrain {
+    type State = MepState;
+
+    fn random(shape: &BrainShape, opcodes: &OpcodeTable, rng: &mut R) -> Self {
+        let program = MepBrain::random_program(shape, opcodes, rng);
+        MepBrain::build(shape, shape.mutate_size, shape.crossover_points, rng, program.into_iter())
+    }
+
+    // Every program is drawn before any of the brains are built, which is the order bots have always been made in
+    fn random_all(shapes: &[&BrainShape], opcodes: &OpcodeTable, rng: &mut R) -> Vec<Self> {
+        let programs = shapes.iter().map(|shape| MepBrain::random_program(shape, opcodes, rng)).collect::<Vec<_>>();
+        shapes.iter()
+            .zip(programs)
+            .map(|(shape, program)| {
+                MepBrain::build(shape, shape.mutate_size, shape.crossover_points, rng, program.into_iter())
+            })
+            .collect()
+    }
+
+    fn compute(&self, arithmetic: Arithmetic, inputs: &[i64], outputs: &mut [i64]) {
+        self.tape.compute(arithmetic, inputs, outputs);
+    }
+
+    fn mutate(&mut self, opcodes: &OpcodeTable, rng: &mut R) {
+        // mli mutates instructions with `mutator`, which draws from the table of the scope it is called in
+        let mep = &mut self.mep;
+        opcodes.scope(|| mli::Genetic::mutate(mep, rng));
+        self.tape = Tape::compile(&self.mep.program, self.inputs, self.outputs);
+    }
+
+    fn crossover(&self, other: &Self, rng: &mut R) -> Self {
+        MepBrain::from_mep(mli::Genetic::mate((&self.mep, &other.mep), rng), self.inputs, self.outputs)
+    }
+
+    fn state(&self) -> MepState {
+        MepState {
+            program: self.mep.program.clone(),
+            unit_mutate_size: self.mep.unit_mutate_size,
+            crossover_points: self.mep.crossover_points,
+        }
+    }
+
+    fn from_state(state: MepState, shape: &BrainShape) -> Self {
+        // The instructions are replaced afterwards, so the generator used to build the program doesn't matter
+        let mut scratch = R::new(&[0]);
+        let mut mep = MepBrain::build(shape,
+                                      state.unit_mutate_size,
+                                      state.crossover_points,
+                                      &mut scratch,
+                                      state.program.iter().map(|&(ins, _, _)| ins))
+            .mep;
+        mep.program = state.program;
+        MepBrain::from_mep(mep, shape.inputs, shape.outputs)
+    }
+
+    fn len(&self) -> usize {
+        self.mep.program.len()
+    }
+
+    fn analyze(&self) -> BrainAnalysis {
+        BrainAnalysis::of_program(&self.mep.program, self.inputs, self.outputs)
+    }
+
+    fn listing(&self) -> Vec<Listing> {
+        let len = self.mep.program.len();
+        // The outputs are taken from the end of the program
+        let first_output = len.saturating_sub(self.outputs);
+        let operand = |o: usize| if o < self.inputs { Operand::Input(o) } else { Operand::Result(o - self.inputs) };
+        self.mep
+            .program
+            .iter()
+            .enumerate()
+            .map(|(i, &(ins, a, b))| {
+                Listing {
+                    ins: ins,
+                    operands: [a, b].iter().take(ins.operands()).map(|&o| operand(o)).collect(),
+                    dest: None,
+                    output: if i >= first_output { Some(i - first_output) } else { None },
+                }
+            })
+            .collect()
+    }
+
+    fn mutate_size(&self) -> usize {
+        self.mep.unit_mutate_size
+    }
+
+    fn crossover_points(&self) -> usize {
+        self.mep.crossover_points
+    }
+}
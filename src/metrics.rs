@@ -0,0 +1,185 @@
+use std::collections::VecDeque;
+
+use bot::Brain;
+use world::Graph;
+
+/// The number of buckets in the degree histogram. Bucket `k` counts nodes with `k` connections, and the last bucket
+/// counts every node with more than that.
+pub const DEGREE_BUCKETS: usize = 8;
+
+// How many breadth first searches are used to estimate the diameter
+const DIAMETER_SWEEPS: usize = 4;
+
+pub fn degree_label(b: usize) -> String {
+    if b == DEGREE_BUCKETS - 1 {
+        format!("degree_{}_up", b)
+    } else {
+        format!("degree_{}", b)
+    }
+}
+
+/// Measures of the shape of the node network.
+#[derive(Clone, Debug)]
+pub struct GraphMetrics {
+    /// The number of nodes by `Node::connections`, bucketed as described by `DEGREE_BUCKETS`.
+    pub degrees: [u64; DEGREE_BUCKETS],
+    /// The number of connected components.
+    pub components: usize,
+    /// The number of nodes in the largest connected component.
+    pub largest_component: usize,
+    /// The mean over all nodes of the fraction of pairs of a node's neighbors that are connected to each other.
+    /// Nodes with fewer than two neighbors count as 0.
+    pub clustering: f64,
+    /// A lower bound on the diameter of the largest component found with a few breadth first searches, which is
+    /// usually exact for meshes like these.
+    pub diameter: usize,
+}
+
+/// The neighbors of every node by index, in order and without duplicates.
+fn adjacency<B: Brain>(deps: &Graph<B>) -> Vec<Vec<usize>> {
+    deps.node_indices()
+        .map(|i| {
+            let mut neighbors = deps.neighbors(i).map(|n| n.index()).filter(|&n| n != i.index()).collect::<Vec<_>>();
+            neighbors.sort();
+            neighbors.dedup();
+            neighbors
+        })
+        .collect()
+}
+
+/// The distance of every node from `start`, or None for nodes that can't be reached.
+fn distances(adjacency: &[Vec<usize>], start: usize) -> Vec<Option<usize>> {
+    let mut distance = vec![None; adjacency.len()];
+    let mut queue = VecDeque::new();
+    distance[start] = Some(0);
+    queue.push_back(start);
+    while let Some(i) = queue.pop_front() {
+        let d = distance[i].unwrap();
+        for &n in &adjacency[i] {
+            if distance[n].is_none() {
+                distance[n] = Some(d + 1);
+                queue.push_back(n);
+            }
+        }
+    }
+    distance
+}
+
+impl GraphMetrics {
+    pub fn new<B: Brain>(deps: &Graph<B>) -> Self {
+        let adjacency = adjacency(deps);
+        let mut metrics = GraphMetrics {
+            degrees: [0; DEGREE_BUCKETS],
+            components: 0,
+            largest_component: 0,
+            clustering: 0.0,
+            diameter: 0,
+        };
+        for n in deps.node_weights() {
+            let degree = if n.connections < 0 { 0 } else { n.connections as usize };
+            metrics.degrees[::std::cmp::min(degree, DEGREE_BUCKETS - 1)] += 1;
+        }
+
+        // Find the components by flooding out from every node not yet reached, remembering a node in the largest
+        let mut reached = vec![false; adjacency.len()];
+        let mut largest = None;
+        for start in 0..adjacency.len() {
+            if reached[start] {
+                continue;
+            }
+            reached[start] = true;
+            let mut stack = vec![start];
+            let mut size = 0;
+            while let Some(i) = stack.pop() {
+                size += 1;
+                for &n in &adjacency[i] {
+                    if !reached[n] {
+                        reached[n] = true;
+                        stack.push(n);
+                    }
+                }
+            }
+            if size > metrics.largest_component {
+                metrics.largest_component = size;
+                largest = Some(start);
+            }
+            metrics.components += 1;
+        }
+
+        let mut clustering = 0.0;
+        for neighbors in &adjacency {
+            let k = neighbors.len();
+            if k < 2 {
+                continue;
+            }
+            let mut links = 0;
+            for (x, &a) in neighbors.iter().enumerate() {
+                for &b in &neighbors[x + 1..] {
+                    if adjacency[a].binary_search(&b).is_ok() {
+                        links += 1;
+                    }
+                }
+            }
+            clustering += links as f64 / (k * (k - 1) / 2) as f64;
+        }
+        if !adjacency.is_empty() {
+            metrics.clustering = clustering / adjacency.len() as f64;
+        }
+
+        // Each search starts from the farthest node found by the last one until that stops finding farther nodes
+        if let Some(mut start) = largest {
+            for _ in 0..DIAMETER_SWEEPS {
+                let (far, d) = distances(&adjacency, start)
+                    .into_iter()
+                    .enumerate()
+                    .filter_map(|(i, d)| d.map(|d| (i, d)))
+                    .max_by_key(|&(i, d)| (d, !i))
+                    .unwrap();
+                if d <= metrics.diameter {
+                    break;
+                }
+                metrics.diameter = d;
+                start = far;
+            }
+        }
+        metrics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use petgraph;
+    use petgraph::graph::NodeIndex;
+    use zoom;
+
+    use super::GraphMetrics;
+    use node::Node;
+    use world::Graph;
+
+    // A path of four nodes, a triangle and a node on its own
+    fn example() -> Graph {
+        let mut deps: Graph = petgraph::Graph::new_undirected();
+        for id in 0..8 {
+            deps.add_node(Node::new(id, 0, zoom::BasicParticle::default()));
+        }
+        for &(a, b) in &[(0, 1), (1, 2), (2, 3), (4, 5), (5, 6), (6, 4)] {
+            deps.add_edge(NodeIndex::new(a), NodeIndex::new(b), ());
+        }
+        for i in deps.node_indices() {
+            deps[i].connections = deps.neighbors(i).count() as i64;
+        }
+        deps
+    }
+
+    #[test]
+    fn path_and_triangle() {
+        let metrics = GraphMetrics::new(&example());
+        assert_eq!(metrics.degrees, [1, 2, 5, 0, 0, 0, 0, 0]);
+        assert_eq!(metrics.components, 3);
+        assert_eq!(metrics.largest_component, 4);
+        // Only the three nodes of the triangle have neighbors that are connected to each other
+        assert_eq!(metrics.clustering, 3.0 / 8.0);
+        // The ends of the path
+        assert_eq!(metrics.diameter, 3);
+    }
+}
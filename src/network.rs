@@ -0,0 +1,323 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use na::Norm;
+use zoom::Toroid;
+
+use bot::Brain;
+use node::Node;
+use world::World;
+
+/// The attributes exported for every node. Nodes are identified by `Node::id`, which stays the same for the whole run.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NodeAttributes {
+    pub energy: i64,
+    pub bots: usize,
+    pub connections: i64,
+    pub pull: i64,
+    pub deaths: i64,
+    pub moves: i64,
+    pub oldforce: f64,
+    pub position: [f64; 3],
+}
+
+// The name of every node attribute and its type in GraphML and in GEXF, in the order of `NodeAttributes::values`
+const NODE_ATTRIBUTES: [(&'static str, &'static str, &'static str); 10] = [("energy", "long", "long"),
+                                                                          ("bots", "int", "integer"),
+                                                                          ("connections", "long", "long"),
+                                                                          ("pull", "long", "long"),
+                                                                          ("deaths", "long", "long"),
+                                                                          ("moves", "long", "long"),
+                                                                          ("oldforce", "double", "double"),
+                                                                          ("x", "double", "double"),
+                                                                          ("y", "double", "double"),
+                                                                          ("z", "double", "double")];
+
+impl NodeAttributes {
+    pub fn of<B: Brain>(node: &Node<B>) -> Self {
+        let p = node.particle.p.position;
+        NodeAttributes {
+            energy: node.energy,
+            bots: node.bots.len(),
+            connections: node.connections,
+            pull: node.pull,
+            deaths: node.deaths,
+            moves: node.moves,
+            oldforce: node.oldforce,
+            position: [p.x, p.y, p.z],
+        }
+    }
+
+    fn values(&self) -> [String; 10] {
+        [self.energy.to_string(),
+         self.bots.to_string(),
+         self.connections.to_string(),
+         self.pull.to_string(),
+         self.deaths.to_string(),
+         self.moves.to_string(),
+         self.oldforce.to_string(),
+         self.position[0].to_string(),
+         self.position[1].to_string(),
+         self.position[2].to_string()]
+    }
+}
+
+/// Every node of the world by ID.
+fn nodes<B: Brain>(world: &World<B>) -> BTreeMap<u64, NodeAttributes> {
+    world.deps.node_weights().map(|n| (n.id, NodeAttributes::of(n))).collect()
+}
+
+/// Every edge of the world by the IDs of its nodes, smaller first, with its length measured around the space as the
+/// edge springs measure it.
+fn edges<B: Brain>(world: &World<B>) -> BTreeMap<(u64, u64), f64> {
+    let space = world.config.node_space();
+    world.deps
+        .edge_indices()
+        .map(|e| {
+            let (a, b) = world.deps.edge_endpoints(e).unwrap();
+            let (a, b) = (&world.deps[a], &world.deps[b]);
+            let length = space.wrap_delta(b.particle.p.position - a.particle.p.position).norm();
+            ((::std::cmp::min(a.id, b.id), ::std::cmp::max(a.id, b.id)), length)
+        })
+        .collect()
+}
+
+/// Write the mesh of a world as GraphML.
+pub fn write_graphml<B: Brain, W: Write>(world: &World<B>, mut w: W) -> io::Result<()> {
+    writeln!(w, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+    writeln!(w, "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">")?;
+    for &(name, kind, _) in &NODE_ATTRIBUTES {
+        writeln!(w, "  <key id=\"{0}\" for=\"node\" attr.name=\"{0}\" attr.type=\"{1}\"/>", name, kind)?;
+    }
+    writeln!(w, "  <key id=\"length\" for=\"edge\" attr.name=\"length\" attr.type=\"double\"/>")?;
+    writeln!(w, "  <graph id=\"tick{}\" edgedefault=\"undirected\">", world.period)?;
+    for (id, attributes) in nodes(world) {
+        writeln!(w, "    <node id=\"n{}\">", id)?;
+        for (&(name, _, _), value) in NODE_ATTRIBUTES.iter().zip(attributes.values().iter()) {
+            writeln!(w, "      <data key=\"{}\">{}</data>", name, value)?;
+        }
+        writeln!(w, "    </node>")?;
+    }
+    for ((a, b), length) in edges(world) {
+        writeln!(w, "    <edge source=\"n{}\" target=\"n{}\">", a, b)?;
+        writeln!(w, "      <data key=\"length\">{}</data>", length)?;
+        writeln!(w, "    </edge>")?;
+    }
+    writeln!(w, "  </graph>")?;
+    writeln!(w, "</graphml>")
+}
+
+fn write_gexf_header<W: Write>(w: &mut W, mode: &str) -> io::Result<()> {
+    writeln!(w, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+    writeln!(w, "<gexf xmlns=\"http://www.gexf.net/1.2draft\" version=\"1.2\">")?;
+    if mode == "dynamic" {
+        writeln!(w, "  <graph mode=\"dynamic\" defaultedgetype=\"undirected\" timeformat=\"double\">")?;
+    } else {
+        writeln!(w, "  <graph mode=\"static\" defaultedgetype=\"undirected\">")?;
+    }
+    writeln!(w, "    <attributes class=\"node\" mode=\"{}\">", mode)?;
+    for (i, &(name, _, kind)) in NODE_ATTRIBUTES.iter().enumerate() {
+        writeln!(w, "      <attribute id=\"{}\" title=\"{}\" type=\"{}\"/>", i, name, kind)?;
+    }
+    writeln!(w, "    </attributes>")?;
+    writeln!(w, "    <attributes class=\"edge\" mode=\"{}\">", mode)?;
+    writeln!(w, "      <attribute id=\"0\" title=\"length\" type=\"double\"/>")?;
+    writeln!(w, "    </attributes>")
+}
+
+/// Write the mesh of a world as a static GEXF graph.
+pub fn write_gexf<B: Brain, W: Write>(world: &World<B>, mut w: W) -> io::Result<()> {
+    write_gexf_header(&mut w, "static")?;
+    writeln!(w, "    <nodes>")?;
+    for (id, attributes) in nodes(world) {
+        writeln!(w, "      <node id=\"{0}\" label=\"{0}\">", id)?;
+        writeln!(w, "        <attvalues>")?;
+        for (i, value) in attributes.values().iter().enumerate() {
+            writeln!(w, "          <attvalue for=\"{}\" value=\"{}\"/>", i, value)?;
+        }
+        writeln!(w, "        </attvalues>")?;
+        writeln!(w, "      </node>")?;
+    }
+    writeln!(w, "    </nodes>")?;
+    writeln!(w, "    <edges>")?;
+    for (i, ((a, b), length)) in edges(world).into_iter().enumerate() {
+        writeln!(w, "      <edge id=\"{}\" source=\"{}\" target=\"{}\">", i, a, b)?;
+        writeln!(w, "        <attvalues><attvalue for=\"0\" value=\"{}\"/></attvalues>", length)?;
+        writeln!(w, "      </edge>")?;
+    }
+    writeln!(w, "    </edges>")?;
+    writeln!(w, "  </graph>")?;
+    writeln!(w, "</gexf>")
+}
+
+pub fn save_graphml<B: Brain, P: AsRef<Path>>(world: &World<B>, path: P) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_graphml(world, &mut writer)?;
+    writer.flush()
+}
+
+pub fn save_gexf<B: Brain, P: AsRef<Path>>(world: &World<B>, path: P) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_gexf(world, &mut writer)?;
+    writer.flush()
+}
+
+/// When something was in the mesh and the values it had, each from the tick it was sampled at until the next value.
+struct History<T> {
+    // Start and end ticks; the end is None while it is still in the mesh
+    spells: Vec<(u64, Option<u64>)>,
+    values: Vec<(u64, T)>,
+}
+
+impl<T: PartialEq> History<T> {
+    fn new() -> Self {
+        History {
+            spells: Vec::new(),
+            values: Vec::new(),
+        }
+    }
+
+    fn present(&mut self, tick: u64, value: T) {
+        if self.spells.last().map(|&(_, end)| end.is_some()).unwrap_or(true) {
+            self.spells.push((tick, None));
+        }
+        // Only changes are kept
+        if self.values.last().map(|&(_, ref v)| *v != value).unwrap_or(true) {
+            self.values.push((tick, value));
+        }
+    }
+
+    fn absent(&mut self, tick: u64) {
+        if let Some(spell) = self.spells.last_mut() {
+            if spell.1.is_none() {
+                spell.1 = Some(tick);
+            }
+        }
+    }
+
+    /// The spells with every open spell ending at `end`.
+    fn closed_spells(&self, end: u64) -> Vec<(u64, u64)> {
+        self.spells.iter().map(|&(s, e)| (s, e.unwrap_or(end))).collect()
+    }
+
+    /// The values with the tick each stops applying at.
+    fn closed_values(&self, end: u64) -> Vec<(u64, u64, &T)> {
+        self.values
+            .iter()
+            .enumerate()
+            .map(|(i, &(s, ref v))| (s, self.values.get(i + 1).map(|&(e, _)| e).unwrap_or(end), v))
+            .collect()
+    }
+}
+
+/// Samples the mesh every few ticks and writes its history as a dynamic GEXF graph, in which nodes and edges appear
+/// and disappear and node attributes and edge lengths change over time.
+///
+/// The whole history is kept in memory until it is saved, so long runs should be sampled sparingly.
+pub struct GexfRecorder {
+    every: u64,
+    nodes: BTreeMap<u64, History<NodeAttributes>>,
+    edges: BTreeMap<(u64, u64), History<f64>>,
+    last: Option<u64>,
+}
+
+impl GexfRecorder {
+    pub fn new(every: u64) -> Self {
+        GexfRecorder {
+            every: every,
+            nodes: BTreeMap::new(),
+            edges: BTreeMap::new(),
+            last: None,
+        }
+    }
+
+    /// Sample the world if it is on a tick that should be sampled.
+    pub fn tick<B: Brain>(&mut self, world: &World<B>) {
+        if self.every != 0 && world.period % self.every == 0 {
+            self.record(world);
+        }
+    }
+
+    /// Sample the world now.
+    pub fn record<B: Brain>(&mut self, world: &World<B>) {
+        let tick = world.period;
+        if self.last == Some(tick) {
+            return;
+        }
+        self.last = Some(tick);
+        let nodes = nodes(world);
+        for (&id, history) in &mut self.nodes {
+            if !nodes.contains_key(&id) {
+                history.absent(tick);
+            }
+        }
+        for (id, attributes) in nodes {
+            self.nodes.entry(id).or_insert_with(History::new).present(tick, attributes);
+        }
+        let edges = edges(world);
+        for (pair, history) in &mut self.edges {
+            if !edges.contains_key(pair) {
+                history.absent(tick);
+            }
+        }
+        for (pair, length) in edges {
+            self.edges.entry(pair).or_insert_with(History::new).present(tick, length);
+        }
+    }
+
+    /// Write the history sampled so far. Whatever is still in the mesh is shown until the tick after the last sample.
+    pub fn write<W: Write>(&self, mut w: W) -> io::Result<()> {
+        let end = self.last.map(|t| t + 1).unwrap_or(0);
+        write_gexf_header(&mut w, "dynamic")?;
+        writeln!(w, "    <nodes>")?;
+        for (id, history) in &self.nodes {
+            // Nodes are never brought back once they are gone, so they only ever have one spell
+            let (start, stop) = history.closed_spells(end)[0];
+            writeln!(w, "      <node id=\"{0}\" label=\"{0}\" start=\"{1}\" endopen=\"{2}\">", id, start, stop)?;
+            writeln!(w, "        <attvalues>")?;
+            for (s, e, attributes) in history.closed_values(end) {
+                for (i, value) in attributes.values().iter().enumerate() {
+                    writeln!(w,
+                             "          <attvalue for=\"{}\" value=\"{}\" start=\"{}\" endopen=\"{}\"/>",
+                             i,
+                             value,
+                             s,
+                             e)?;
+                }
+            }
+            writeln!(w, "        </attvalues>")?;
+            writeln!(w, "      </node>")?;
+        }
+        writeln!(w, "    </nodes>")?;
+        writeln!(w, "    <edges>")?;
+        for (i, (&(a, b), history)) in self.edges.iter().enumerate() {
+            writeln!(w, "      <edge id=\"{}\" source=\"{}\" target=\"{}\">", i, a, b)?;
+            writeln!(w, "        <attvalues>")?;
+            for (s, e, length) in history.closed_values(end) {
+                writeln!(w,
+                         "          <attvalue for=\"0\" value=\"{}\" start=\"{}\" endopen=\"{}\"/>",
+                         length,
+                         s,
+                         e)?;
+            }
+            writeln!(w, "        </attvalues>")?;
+            writeln!(w, "        <spells>")?;
+            for (s, e) in history.closed_spells(end) {
+                writeln!(w, "          <spell start=\"{}\" endopen=\"{}\"/>", s, e)?;
+            }
+            writeln!(w, "        </spells>")?;
+            writeln!(w, "      </edge>")?;
+        }
+        writeln!(w, "    </edges>")?;
+        writeln!(w, "  </graph>")?;
+        writeln!(w, "</gexf>")
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write(&mut writer)?;
+        writer.flush()
+    }
+}
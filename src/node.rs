@@ -1,23 +1,12 @@
 extern crate zoom;
-extern crate rand;
 
 use super::bot::*;
-use super::{Vec3, SIZE_FACTOR};
+use super::Vec3;
+use super::config::SimConfig;
 
 const BOTS_RADIUS_MULTIPLIER: f32 = 5.0;
 const RADIUS_STATIC: f32 = 5.0;
-const ENERGY_RATIO_NET: f64 = 2.0 * 20.0 * SIZE_FACTOR * SIZE_FACTOR * SIZE_FACTOR;
-const ENERGY_RATIO_SINGLE_LIMIT: f64 = 1.0;
-const ENERGY_VARIATION: f64 = 0.2;
-pub const ENERGY_THRESHOLD: i64 = 5000000;
-const ENERGY_FULL_COST: i64 = 50000;
-
-const EDGE_FOOD_BENEFIT: f64 = 0.0;
-const HAVE_EDGE_FOOD_BENEFIT: f64 = 0.0;
-const HAVE_THREE_EDGE_FOOD_BENEFIT: f64 = 0.0;
-const EDGE_DIFFUSION_COEFFICIENT: f64 = 0.05;
-
-const DRAG: f64 = 0.4;
+
 const PHYSICS_RADIUS: f64 = 5.0;
 const INERTIA: f64 = 25.0;
 
@@ -68,11 +57,13 @@ impl zoom::Ball<f64> for RadParticle {
     }
 }
 
-pub struct Node {
+pub struct Node<B: Brain = MepBrain> {
+    // Unique for the whole run, unlike the node's index in the graph, which changes as other nodes are removed
+    pub id: u64,
     pub particle: RadParticle,
     pub energy: i64,
-    pub bots: Vec<Box<Bot>>,
-    pub moved_bots: Vec<Box<Bot>>,
+    pub bots: Vec<Box<Bot<B>>>,
+    pub moved_bots: Vec<Box<Bot<B>>>,
     pub deaths: i64,
     pub moves: i64,
     pub connections: i64,
@@ -82,17 +73,45 @@ pub struct Node {
     pub oldforce: f64,
 }
 
-fn growlimit(rate: f64) -> f64 {
-    if rate > ENERGY_RATIO_SINGLE_LIMIT {
-        ENERGY_RATIO_SINGLE_LIMIT
+/// The serializable form of a node, including the bots in it. `S` is the serializable form of the bots' brains.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct NodeState<S = MepState> {
+    pub id: u64,
+    pub position: [f64; 3],
+    pub velocity: [f64; 3],
+    // Impulse applied to the particle that will take effect when it next advances
+    pub impulse: [f64; 3],
+    pub energy: i64,
+    pub bots: Vec<BotState<S>>,
+    pub moved_bots: Vec<BotState<S>>,
+    pub deaths: i64,
+    pub moves: i64,
+    pub connections: i64,
+    pub pull: i64,
+    pub diffuse: i64,
+    pub oldforce: f64,
+}
+
+fn vec_to_array(v: Vec3) -> [f64; 3] {
+    [v.x, v.y, v.z]
+}
+
+fn array_to_vec(a: [f64; 3]) -> Vec3 {
+    Vec3::new(a[0], a[1], a[2])
+}
+
+fn growlimit(rate: f64, config: &SimConfig) -> f64 {
+    if rate > config.energy_ratio_single_limit {
+        config.energy_ratio_single_limit
     } else {
         rate
     }
 }
 
-impl Node {
-    pub fn new(energy: i64, particle: zoom::BasicParticle<Vec3, f64>) -> Self {
+impl<B: Brain> Node<B> {
+    pub fn new(id: u64, energy: i64, particle: zoom::BasicParticle<Vec3, f64>) -> Self {
         Node {
+            id: id,
             energy: energy,
             particle: RadParticle { p: particle },
             bots: Vec::new(),
@@ -106,52 +125,86 @@ impl Node {
         }
     }
 
-    pub fn diffuse(&mut self) {
-        self.diffuse = self.connections * (self.energy as f64 * EDGE_DIFFUSION_COEFFICIENT) as i64;
+    pub fn state(&self) -> NodeState<B::State> {
+        NodeState {
+            id: self.id,
+            position: vec_to_array(self.particle.p.position),
+            velocity: vec_to_array(self.particle.p.velocity),
+            impulse: vec_to_array(self.particle.p.impulse.get()),
+            energy: self.energy,
+            bots: self.bots.iter().map(|b| b.state()).collect(),
+            moved_bots: self.moved_bots.iter().map(|b| b.state()).collect(),
+            deaths: self.deaths,
+            moves: self.moves,
+            connections: self.connections,
+            pull: self.pull,
+            diffuse: self.diffuse,
+            oldforce: self.oldforce,
+        }
+    }
+
+    pub fn from_state(state: NodeState<B::State>) -> Self {
+        let mut particle = zoom::BasicParticle::default();
+        particle.position = array_to_vec(state.position);
+        particle.velocity = array_to_vec(state.velocity);
+        particle.impulse.set(array_to_vec(state.impulse));
+        let mut node = Node::new(state.id, state.energy, particle);
+        node.bots = state.bots.into_iter().map(|b| Box::new(Bot::from_state(b))).collect();
+        node.moved_bots = state.moved_bots.into_iter().map(|b| Box::new(Bot::from_state(b))).collect();
+        node.deaths = state.deaths;
+        node.moves = state.moves;
+        node.connections = state.connections;
+        node.pull = state.pull;
+        node.diffuse = state.diffuse;
+        node.oldforce = state.oldforce;
+        node
+    }
+
+    pub fn diffuse(&mut self, config: &SimConfig) {
+        self.diffuse = self.connections * (self.energy as f64 * config.edge_diffusion_coefficient) as i64;
         self.energy -= self.diffuse;
     }
 
-    pub fn grow(&mut self, capped: bool, total_nodes: usize, rng: &mut rand::Isaac64Rng) {
+    pub fn grow(&mut self, capped: bool, total_nodes: usize, rng: &mut R, config: &SimConfig) {
         use rand::Rng;
         if capped && self.bots.is_empty() {
-            self.energy = self.energy.saturating_sub(ENERGY_FULL_COST);
+            self.energy = self.energy.saturating_sub(config.energy_full_cost);
         } else {
-            self.energy = self.energy.saturating_add((self.energy as f64 * growlimit(ENERGY_RATIO_NET / total_nodes as f64 *
+            self.energy = self.energy.saturating_add((self.energy as f64 * growlimit(config.energy_ratio_net() / total_nodes as f64 *
                 //Create rate differential
-                (1.0 + rng.gen_range(-ENERGY_VARIATION, ENERGY_VARIATION) +
+                (1.0 + rng.gen_range(-config.energy_variation, config.energy_variation) +
                     //Add food for having more connections
-                    EDGE_FOOD_BENEFIT * self.connections as f64 +
+                    config.edge_food_benefit * self.connections as f64 +
                     //Add food for having any connections
                     if self.connections != 0 {
-                        HAVE_EDGE_FOOD_BENEFIT
+                        config.have_edge_food_benefit
                     } else {
                         0.0
                     } +
                     if self.connections == 3 {
-                        HAVE_THREE_EDGE_FOOD_BENEFIT
+                        config.have_three_edge_food_benefit
                     } else {
                         0.0
-                    })
-                )
+                    }),
+                config)
             ) as i64);
         }
     }
 
-    pub fn advance(&mut self) {
+    pub fn advance(&mut self, config: &SimConfig) {
         use na::Norm;
         use zoom::{Particle, Velocity, PhysicsParticle, Toroid};
-        use super::NODE_SPACE;
-        self.particle.drag(DRAG);
+        self.particle.drag(config.drag);
         let oldvel = self.particle.velocity();
         self.particle.advance(1.0);
         let newvel = self.particle.velocity();
         // Get force including changes from time delta
         self.oldforce = (newvel - oldvel).norm();
-        self.particle.p.position = NODE_SPACE.wrap_position(self.particle.p.position);
+        self.particle.p.position = config.node_space().wrap_position(self.particle.p.position);
     }
 
-    pub fn should_split(&self) -> bool {
-        self.energy >= ENERGY_THRESHOLD
+    pub fn should_split(&self, config: &SimConfig) -> bool {
+        self.energy >= config.energy_threshold
     }
     pub fn should_obliterate(&self) -> bool {
         use std::num::FpCategory;
@@ -168,9 +221,9 @@ impl Node {
         }
     }
 
-    pub fn color(&self) -> [f32; 4] {
-        [1.0 - self.energy as f32 / ENERGY_THRESHOLD as f32,
-         self.energy as f32 / ENERGY_THRESHOLD as f32,
+    pub fn color(&self, config: &SimConfig) -> [f32; 4] {
+        [1.0 - self.energy as f32 / config.energy_threshold as f32,
+         self.energy as f32 / config.energy_threshold as f32,
          if self.bots.len() == 0 {
              0.0
          } else {
@@ -0,0 +1,199 @@
+use std::path::PathBuf;
+use std::process;
+
+use evobots::World;
+use evobots::autosave::Autosave;
+use evobots::stats::Statistics;
+use evobots::frames::FrameRecorder;
+use evobots::network::GexfRecorder;
+use evobots::lineage::LineageLog;
+use evobots::phylogeny::Phylogeny;
+use evobots::replay::{DigestRecorder, DigestVerifier, TickDigest, Verification};
+
+use cli::Options;
+
+// How many ticks pass between prunings of extinct lineages from the phylogeny
+const PRUNE_INTERVAL: u64 = 1000;
+
+fn exit_with(message: String) -> ! {
+    println!("{}", message);
+    process::exit(1);
+}
+
+/// Everything that watches or records the simulation as it runs.
+pub struct Outputs {
+    out: Option<PathBuf>,
+    autosave: Option<Autosave>,
+    stats: Option<Statistics>,
+    frames: Option<FrameRecorder>,
+    gexf: Option<(PathBuf, GexfRecorder)>,
+    lineage: Option<LineageLog>,
+    phylogeny: Option<Phylogeny>,
+    prune_extinct: bool,
+    recorder: Option<DigestRecorder>,
+    verifier: Option<DigestVerifier>,
+}
+
+impl Outputs {
+    pub fn new(options: &Options, world: &mut World) -> Self {
+        let autosave = options.autosave_every.map(|every| {
+            let dir = options.out.as_ref().unwrap().join("autosave");
+            Autosave::new(&dir, every, options.autosave_keep).unwrap_or_else(|e| {
+                exit_with(format!("Unable to create autosave directory {}: {}", dir.display(), e))
+            })
+        });
+        let stats = options.stats_every.map(|every| {
+            let path = options.out.as_ref().unwrap().join(format!("stats.{}", options.stats_format.extension()));
+            let stats = if options.resume {
+                Statistics::resume(&path, options.stats_format, every, world)
+            } else {
+                Statistics::create(&path, options.stats_format, every, world)
+            };
+            stats.unwrap_or_else(|e| {
+                exit_with(format!("Unable to create statistics file {}: {}", path.display(), e))
+            })
+        });
+        let frames = options.frames_every.map(|every| {
+            let dir = options.out.as_ref().unwrap().join("frames");
+            FrameRecorder::new(&dir, every, options.frame_width, options.frame_height).unwrap_or_else(|e| {
+                exit_with(format!("Unable to create frame directory {}: {}", dir.display(), e))
+            })
+        });
+        let gexf = options.gexf_every.map(|every| (options.gexf.clone().unwrap(), GexfRecorder::new(every)));
+        let lineage = if options.lineage {
+            let path = options.out.as_ref().unwrap().join("lineage.jsonl");
+            world.lineage.set_recording(true);
+            let log = if options.resume {
+                LineageLog::resume(&path, world.period)
+            } else {
+                LineageLog::create(&path)
+            };
+            Some(log.unwrap_or_else(|e| {
+                exit_with(format!("Unable to create lineage log {}: {}", path.display(), e))
+            }))
+        } else {
+            None
+        };
+        let phylogeny = if options.phylogeny {
+            world.lineage.set_recording(true);
+            Some(Phylogeny::new())
+        } else {
+            None
+        };
+        let recorder = options.record_digests.as_ref().map(|path| {
+            DigestRecorder::create(path).unwrap_or_else(|e| {
+                exit_with(format!("Unable to create digest log {}: {}", path.display(), e))
+            })
+        });
+        let verifier = options.verify_digests.as_ref().map(|path| {
+            DigestVerifier::open(path).unwrap_or_else(|e| {
+                exit_with(format!("Unable to open digest log {}: {}", path.display(), e))
+            })
+        });
+        Outputs {
+            out: options.out.clone(),
+            autosave: autosave,
+            stats: stats,
+            frames: frames,
+            gexf: gexf,
+            lineage: lineage,
+            phylogeny: phylogeny,
+            prune_extinct: options.prune_extinct,
+            recorder: recorder,
+            verifier: verifier,
+        }
+    }
+
+    /// Run one tick of the world and record it. Returns false when the run should stop.
+    pub fn step(&mut self, world: &mut World) -> bool {
+        if self.recorder.is_none() && self.verifier.is_none() {
+            world.step();
+        } else {
+            let digest = TickDigest::step(world);
+            if let Some(ref mut recorder) = self.recorder {
+                if let Err(e) = recorder.record(&digest) {
+                    exit_with(format!("Unable to write digest log: {}", e));
+                }
+            }
+            if let Some(ref mut verifier) = self.verifier {
+                match verifier.verify(&digest) {
+                    Ok(Verification::Matched) => {}
+                    Ok(Verification::Diverged(phase)) => {
+                        exit_with(format!("Diverged from the recorded run at tick {} in the {} phase",
+                                          digest.period,
+                                          phase.name()));
+                    }
+                    Ok(Verification::Missing) => {
+                        println!("The digest log has no entry for tick {}", digest.period);
+                    }
+                    Ok(Verification::Finished) => {
+                        println!("Matched the recorded run up to tick {}", digest.period);
+                        return false;
+                    }
+                    Err(e) => exit_with(format!("Unable to read digest log: {}", e)),
+                }
+            }
+        }
+
+        if let Some(ref mut stats) = self.stats {
+            if let Err(e) = stats.tick(world) {
+                exit_with(format!("Unable to write statistics: {}", e));
+            }
+        }
+
+        if let Some(ref frames) = self.frames {
+            if let Err(e) = frames.tick(world) {
+                exit_with(format!("Unable to write frame: {}", e));
+            }
+        }
+
+        if let Some((_, ref mut gexf)) = self.gexf {
+            gexf.tick(world);
+        }
+
+        let events = world.lineage.drain_events().collect::<Vec<_>>();
+        if let Some(ref mut lineage) = self.lineage {
+            if let Err(e) = lineage.write(events.iter().cloned()) {
+                exit_with(format!("Unable to write lineage log: {}", e));
+            }
+        }
+        if let Some(ref mut phylogeny) = self.phylogeny {
+            for e in &events {
+                phylogeny.record(e);
+            }
+            if self.prune_extinct && world.period % PRUNE_INTERVAL == 0 {
+                phylogeny.prune();
+            }
+        }
+
+        if let Some(ref autosave) = self.autosave {
+            // A failed autosave shouldn't bring down the run it is meant to protect
+            if let Err(e) = autosave.tick(world) {
+                println!("Autosave at tick {} failed: {}", world.period, e);
+            }
+        }
+        true
+    }
+
+    /// Write the outputs that are only written once the run is over.
+    pub fn finish(&mut self, world: &World) {
+        if let Some((ref path, ref mut gexf)) = self.gexf {
+            // Include the end of the run even if it isn't on a sampled tick
+            gexf.record(world);
+            gexf.save(path).unwrap_or_else(|e| exit_with(format!("Unable to write GEXF {}: {}", path.display(), e)));
+        }
+        if let Some(ref mut phylogeny) = self.phylogeny {
+            phylogeny.update_living(world);
+            if self.prune_extinct {
+                phylogeny.prune();
+            }
+            let out = self.out.as_ref().unwrap();
+            let newick = out.join("phylogeny.nwk");
+            let json = out.join("phylogeny.json");
+            phylogeny.save_newick(&newick)
+                .and_then(|_| phylogeny.save_json(&json))
+                .unwrap_or_else(|e| exit_with(format!("Unable to write phylogeny to {}: {}", out.display(), e)));
+            println!("Wrote a phylogeny of {} bots to {}", phylogeny.len(), out.display());
+        }
+    }
+}
@@ -0,0 +1,236 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as FmtWrite;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use serde_json;
+
+use bot::Brain;
+use lineage::Event;
+use world::World;
+
+/// One bot in the evolutionary tree.
+#[derive(Clone, Debug, Serialize)]
+pub struct Taxon {
+    pub id: u64,
+    pub generation: u64,
+    pub parents: Vec<u64>,
+    pub born: u64,
+    /// None while the bot is alive.
+    pub died: Option<u64>,
+    /// The total energy the bot took from nodes. For living bots this is as of the last `update_living`.
+    pub lifetime_energy: i64,
+    pub offspring: u64,
+}
+
+/// The evolutionary tree of a run, built from lineage events.
+///
+/// Bots born by mating have two parents, which makes the history a graph rather than a tree. In the tree the first
+/// parent (the bot that chose to mate) is the parent and the second is only kept in `Taxon::parents`. Bots whose
+/// parent isn't known, such as spawned bots or bots born before the run was resumed, are roots.
+pub struct Phylogeny {
+    taxa: BTreeMap<u64, Taxon>,
+}
+
+impl Phylogeny {
+    pub fn new() -> Self {
+        Phylogeny { taxa: BTreeMap::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.taxa.len()
+    }
+
+    pub fn record(&mut self, event: &Event) {
+        match *event {
+            Event::Birth { tick, id, generation, ref parents } => {
+                for p in parents {
+                    if let Some(t) = self.taxa.get_mut(p) {
+                        t.offspring += 1;
+                    }
+                }
+                self.taxa.insert(id,
+                                 Taxon {
+                                     id: id,
+                                     generation: generation,
+                                     parents: parents.clone(),
+                                     born: tick,
+                                     died: None,
+                                     lifetime_energy: 0,
+                                     offspring: 0,
+                                 });
+            }
+            Event::Death { tick, id, energy, .. } => {
+                if let Some(t) = self.taxa.get_mut(&id) {
+                    t.died = Some(tick);
+                    t.lifetime_energy = energy;
+                }
+            }
+        }
+    }
+
+    /// Bring the lifetime energy of the living bots up to date with the world.
+    pub fn update_living<B: Brain>(&mut self, world: &World<B>) {
+        for n in world.deps.node_weights() {
+            for b in n.bots.iter().chain(n.moved_bots.iter()) {
+                if let Some(t) = self.taxa.get_mut(&b.id) {
+                    t.lifetime_energy = b.consumed;
+                }
+            }
+        }
+    }
+
+    /// Remove every dead bot that has no living descendants through any of its parents.
+    pub fn prune(&mut self) {
+        let mut keep = BTreeSet::new();
+        let mut stack = self.taxa.values().filter(|t| t.died.is_none()).map(|t| t.id).collect::<Vec<_>>();
+        while let Some(id) = stack.pop() {
+            if keep.insert(id) {
+                if let Some(t) = self.taxa.get(&id) {
+                    stack.extend(t.parents.iter().cloned());
+                }
+            }
+        }
+        self.taxa = ::std::mem::replace(&mut self.taxa, BTreeMap::new())
+            .into_iter()
+            .filter(|&(id, _)| keep.contains(&id))
+            .collect();
+    }
+
+    /// The children of every bot in the tree and the roots of the tree, both in ID order.
+    fn tree(&self) -> (BTreeMap<u64, Vec<u64>>, Vec<u64>) {
+        let mut children = BTreeMap::new();
+        let mut roots = Vec::new();
+        for t in self.taxa.values() {
+            match t.parents.first() {
+                Some(p) if self.taxa.contains_key(p) => children.entry(*p).or_insert_with(Vec::new).push(t.id),
+                _ => roots.push(t.id),
+            }
+        }
+        (children, roots)
+    }
+
+    /// The tree in Newick format. Leaves and internal nodes are labeled with bot IDs and branch lengths are the ticks
+    /// between the births of a parent and child.
+    pub fn to_newick(&self) -> String {
+        // Lineages can be thousands of generations deep, so this walks the tree with a stack rather than recursion
+        enum Visit {
+            Enter(u64, bool),
+            Exit(u64),
+        }
+
+        let (children, roots) = self.tree();
+        let mut out = String::new();
+        out.push('(');
+        let mut stack = roots.iter()
+            .enumerate()
+            .rev()
+            .map(|(i, &r)| Visit::Enter(r, i == 0))
+            .collect::<Vec<_>>();
+        while let Some(visit) = stack.pop() {
+            match visit {
+                Visit::Enter(id, first) => {
+                    if !first {
+                        out.push(',');
+                    }
+                    stack.push(Visit::Exit(id));
+                    if let Some(c) = children.get(&id) {
+                        out.push('(');
+                        for (i, &child) in c.iter().enumerate().rev() {
+                            stack.push(Visit::Enter(child, i == 0));
+                        }
+                    }
+                }
+                Visit::Exit(id) => {
+                    if children.contains_key(&id) {
+                        out.push(')');
+                    }
+                    let t = &self.taxa[&id];
+                    let length = t.parents
+                        .first()
+                        .and_then(|p| self.taxa.get(p))
+                        .map(|p| t.born - p.born)
+                        .unwrap_or(0);
+                    write!(out, "{}:{}", id, length).unwrap();
+                }
+            }
+        }
+        out.push_str(");");
+        out
+    }
+
+    pub fn save_newick<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(writer, "{}", self.to_newick())?;
+        writer.flush()
+    }
+
+    /// Save every bot in the tree as a JSON array in ID order.
+    pub fn save_json<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        let taxa = self.taxa.values().collect::<Vec<_>>();
+        serde_json::to_writer_pretty(&mut writer, &taxa).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Phylogeny;
+    use lineage::Event;
+
+    fn birth(tick: u64, id: u64, parents: &[u64]) -> Event {
+        Event::Birth {
+            tick: tick,
+            id: id,
+            generation: parents.len() as u64,
+            parents: parents.to_vec(),
+        }
+    }
+
+    fn death(tick: u64, id: u64) -> Event {
+        Event::Death {
+            tick: tick,
+            id: id,
+            age: 0,
+            energy: 0,
+        }
+    }
+
+    // Bots 1 and 2 are spawned, 5 is mated from 3 and 2, and only 2 and 5 are still alive
+    fn example() -> Phylogeny {
+        let mut phylogeny = Phylogeny::new();
+        let events = [birth(0, 1, &[]),
+                      birth(0, 2, &[]),
+                      birth(3, 6, &[2]),
+                      birth(5, 3, &[1]),
+                      death(7, 6),
+                      birth(8, 4, &[1]),
+                      death(9, 4),
+                      birth(12, 5, &[3, 2]),
+                      death(15, 3),
+                      death(20, 1)];
+        for e in &events {
+            phylogeny.record(e);
+        }
+        phylogeny
+    }
+
+    #[test]
+    fn newick_follows_first_parents() {
+        let phylogeny = example();
+        assert_eq!(phylogeny.len(), 6);
+        assert_eq!(phylogeny.taxa[&1].offspring, 2);
+        assert_eq!(phylogeny.taxa[&2].offspring, 2);
+        assert_eq!(phylogeny.to_newick(), "(((5:7)3:5,4:8)1:0,(6:3)2:0);");
+    }
+
+    #[test]
+    fn prune_keeps_dead_ancestors_of_living_bots() {
+        let mut phylogeny = example();
+        phylogeny.prune();
+        assert_eq!(phylogeny.taxa.keys().cloned().collect::<Vec<_>>(), vec![1, 2, 3, 5]);
+        assert_eq!(phylogeny.to_newick(), "(((5:7)3:5)1:0,2:0);");
+    }
+}
@@ -0,0 +1,181 @@
+use std::fs::File;
+use std::hash::Hasher;
+use std::io::{self, BufRead, BufReader, BufWriter, Lines, Write};
+use std::path::Path;
+
+use bot::Brain;
+use world::{World, Phase, PHASES};
+
+/// 64-bit FNV-1a. The standard library hasher is not guaranteed to be stable between builds, which would make digests
+/// written by one build useless to another.
+struct Fnv(u64);
+
+impl Fnv {
+    fn new() -> Self {
+        Fnv(0xcbf29ce484222325)
+    }
+}
+
+impl Hasher for Fnv {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 ^= b as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+}
+
+/// Hash the parts of the world that any divergence will quickly show up in: node energies, bot counts, the edge set
+/// and how many random numbers have been drawn.
+pub fn digest<B: Brain>(world: &World<B>) -> u64 {
+    let mut hasher = Fnv::new();
+    hasher.write_u64(world.rng.draws());
+    for n in world.deps.raw_nodes() {
+        hasher.write_i64(n.weight.energy);
+        hasher.write_u64(n.weight.bots.len() as u64);
+    }
+    for e in world.deps.raw_edges() {
+        hasher.write_u64(e.source().index() as u64);
+        hasher.write_u64(e.target().index() as u64);
+    }
+    hasher.finish()
+}
+
+/// The digests taken after every phase of one tick.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TickDigest {
+    // The tick the digests were taken during
+    pub period: u64,
+    pub phases: [u64; 6],
+}
+
+impl TickDigest {
+    /// Run one tick of the world and digest it after every phase.
+    pub fn step<B: Brain>(world: &mut World<B>) -> Self {
+        let period = world.period;
+        let mut phases = [0; 6];
+        world.step_observed(|phase, w| phases[phase as usize] = digest(w));
+        TickDigest {
+            period: period,
+            phases: phases,
+        }
+    }
+
+    fn parse(line: &str) -> Option<Self> {
+        let mut parts = line.split_whitespace();
+        let period = parts.next().and_then(|p| p.parse().ok());
+        let mut phases = [0; 6];
+        for p in phases.iter_mut() {
+            *p = match parts.next().and_then(|h| u64::from_str_radix(h, 16).ok()) {
+                Some(h) => h,
+                None => return None,
+            };
+        }
+        period.map(|period| {
+            TickDigest {
+                period: period,
+                phases: phases,
+            }
+        })
+    }
+
+    /// The first phase in which two digests of the same tick differ.
+    pub fn first_difference(&self, other: &TickDigest) -> Option<Phase> {
+        PHASES.iter().cloned().find(|&p| self.phases[p as usize] != other.phases[p as usize])
+    }
+}
+
+/// Writes a digest line for every tick.
+pub struct DigestRecorder {
+    writer: BufWriter<File>,
+}
+
+impl DigestRecorder {
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        write!(writer, "# period")?;
+        for p in &PHASES {
+            write!(writer, " {}", p.name())?;
+        }
+        writeln!(writer, "")?;
+        Ok(DigestRecorder { writer: writer })
+    }
+
+    pub fn record(&mut self, digest: &TickDigest) -> io::Result<()> {
+        write!(self.writer, "{}", digest.period)?;
+        for p in &digest.phases {
+            write!(self.writer, " {:016x}", p)?;
+        }
+        writeln!(self.writer, "")
+    }
+}
+
+/// The outcome of checking one tick against a recorded log.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Verification {
+    Matched,
+    /// The tick differed from the log, first after this phase.
+    Diverged(Phase),
+    /// The log skips this tick.
+    Missing,
+    /// The log has no more ticks.
+    Finished,
+}
+
+/// Compares the digests of a run against a recorded log.
+pub struct DigestVerifier {
+    lines: Lines<BufReader<File>>,
+    pending: Option<TickDigest>,
+}
+
+impl DigestVerifier {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(DigestVerifier {
+            lines: BufReader::new(File::open(path)?).lines(),
+            pending: None,
+        })
+    }
+
+    /// The next recorded digest, or None once the log runs out.
+    fn next_recorded(&mut self) -> io::Result<Option<TickDigest>> {
+        if let Some(d) = self.pending.take() {
+            return Ok(Some(d));
+        }
+        while let Some(line) = self.lines.next() {
+            let line = line?;
+            if line.starts_with('#') || line.trim().is_empty() {
+                continue;
+            }
+            return TickDigest::parse(&line)
+                .map(Some)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("bad digest line: {}", line)));
+        }
+        Ok(None)
+    }
+
+    /// Check the digest of a tick against the log. Recorded ticks before this one are skipped so that a run resumed
+    /// from a snapshot can be verified against a log recorded from the beginning.
+    pub fn verify(&mut self, digest: &TickDigest) -> io::Result<Verification> {
+        loop {
+            let recorded = match self.next_recorded()? {
+                Some(r) => r,
+                None => return Ok(Verification::Finished),
+            };
+            if recorded.period < digest.period {
+                continue;
+            }
+            if recorded.period > digest.period {
+                self.pending = Some(recorded);
+                return Ok(Verification::Missing);
+            }
+            return Ok(match recorded.first_difference(digest) {
+                Some(phase) => Verification::Diverged(phase),
+                None => Verification::Matched,
+            });
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use rand::{Rng, SeedableRng, Isaac64Rng};
+
+/// The random number generator that drives the simulation.
+///
+/// Isaac64 has no way to export its internal state, so this wraps it and counts every number drawn. The exact state
+/// can then be recreated at any point from the seed and the draw count.
+#[derive(Clone)]
+pub struct SimRng {
+    rng: Isaac64Rng,
+    seed: Vec<u64>,
+    draws: u64,
+}
+
+impl SimRng {
+    pub fn new(seed: &[u64]) -> Self {
+        SimRng {
+            rng: Isaac64Rng::from_seed(seed),
+            seed: seed.to_vec(),
+            draws: 0,
+        }
+    }
+
+    /// Recreate the generator that produced `draws` numbers after being seeded with `seed`. Every one of those numbers
+    /// is drawn again, so this takes time in proportion to `draws`.
+    pub fn resume(seed: &[u64], draws: u64) -> Self {
+        let mut rng = SimRng::new(seed);
+        for _ in 0..draws {
+            rng.next_u64();
+        }
+        rng
+    }
+
+    pub fn seed(&self) -> &[u64] {
+        &self.seed
+    }
+
+    /// The amount of numbers drawn since seeding.
+    pub fn draws(&self) -> u64 {
+        self.draws
+    }
+}
+
+impl Rng for SimRng {
+    // Isaac64 produces a full u64 for every u32 requested, so both count as one draw
+    fn next_u32(&mut self) -> u32 {
+        self.draws += 1;
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.draws += 1;
+        self.rng.next_u64()
+    }
+}
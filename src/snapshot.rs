@@ -0,0 +1,176 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use bincode;
+use petgraph;
+use petgraph::graph::NodeIndex;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use config::SimConfig;
+use lineage::Lineage;
+use bot::{Brain, MepState};
+use node::{Node, NodeState};
+use rng::SimRng;
+use world::World;
+
+/// Bump this whenever the layout of `Snapshot` or anything in it changes. The config is stored as TOML, so adding a
+/// config parameter with a default doesn't need a new version.
+pub const SNAPSHOT_VERSION: u32 = 7;
+
+/// Everything needed to continue a run exactly where it left off. `S` is the serializable form of the bots' brains.
+///
+/// The random number generator is stored as its seed and the count of numbers drawn from it, because its internal
+/// state can't be exported. Restoring draws every one of those numbers again, so it takes longer the longer the run
+/// went on: a few seconds for every billion numbers the run drew.
+#[derive(Serialize, Deserialize)]
+pub struct Snapshot<S = MepState> {
+    // Must stay the first field so that it can be checked before decoding the rest
+    pub version: u32,
+    pub seed: Vec<u64>,
+    pub rng_draws: u64,
+    pub period: u64,
+    pub resets: i64,
+    pub divide_births: u64,
+    pub mate_births: u64,
+    pub deaths: i64,
+    pub moves: i64,
+    pub next_bot_id: u64,
+    pub next_node_id: u64,
+    #[serde(with = "toml_config")]
+    pub config: SimConfig,
+    // Nodes and edges are stored in index order so that indices are identical after restoring
+    pub nodes: Vec<NodeState<S>>,
+    pub edges: Vec<(usize, usize)>,
+}
+
+#[derive(Deserialize)]
+struct Header {
+    version: u32,
+}
+
+// Bincode only knows fields by their position, so the config is kept as TOML where parameters are found by name
+mod toml_config {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use serde::de::Error;
+
+    use config::SimConfig;
+
+    pub fn serialize<S: Serializer>(config: &SimConfig, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&config.to_toml())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<SimConfig, D::Error> {
+        SimConfig::from_toml(&String::deserialize(deserializer)?).map_err(D::Error::custom)
+    }
+}
+
+fn invalid<E: ::std::error::Error + Send + Sync + 'static>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+impl<S: Clone + Serialize + DeserializeOwned> Snapshot<S> {
+    pub fn new<B: Brain<State = S>>(world: &World<B>) -> Self {
+        Snapshot {
+            version: SNAPSHOT_VERSION,
+            seed: world.rng.seed().to_vec(),
+            rng_draws: world.rng.draws(),
+            period: world.period,
+            resets: world.resets,
+            divide_births: world.divide_births,
+            mate_births: world.mate_births,
+            deaths: world.deaths,
+            moves: world.moves,
+            next_bot_id: world.lineage.next_id(),
+            next_node_id: world.next_node_id,
+            config: world.config.clone(),
+            nodes: world.deps.raw_nodes().iter().map(|n| n.weight.state()).collect(),
+            edges: world.deps
+                .raw_edges()
+                .iter()
+                .map(|e| (e.source().index(), e.target().index()))
+                .collect(),
+        }
+    }
+
+    /// Rebuild the world this snapshot was taken from. This replays the random number generator from its seed, which
+    /// takes time in proportion to how many numbers the run drew.
+    pub fn restore<B: Brain<State = S>>(self) -> World<B> {
+        let mut world = World::new(&self.seed, self.config);
+        world.rng = SimRng::resume(&self.seed, self.rng_draws);
+        world.period = self.period;
+        world.resets = self.resets;
+        world.divide_births = self.divide_births;
+        world.mate_births = self.mate_births;
+        world.deaths = self.deaths;
+        world.moves = self.moves;
+        world.lineage = Lineage::resume(self.next_bot_id);
+        world.next_node_id = self.next_node_id;
+        let mut deps = petgraph::Graph::new_undirected();
+        for n in self.nodes {
+            deps.add_node(Node::from_state(n));
+        }
+        for (a, b) in self.edges {
+            deps.add_edge(NodeIndex::new(a), NodeIndex::new(b), ());
+        }
+        world.deps = deps;
+        world
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.write_to(File::create(path)?)
+    }
+
+    pub fn write_to<W: Write>(&self, writer: W) -> io::Result<()> {
+        let mut writer = BufWriter::new(writer);
+        bincode::serialize_into(&mut writer, self).map_err(invalid)?;
+        writer.flush()
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Snapshot::read_from(File::open(path)?)
+    }
+
+    pub fn read_from<R: Read>(reader: R) -> io::Result<Self> {
+        let mut bytes = Vec::new();
+        BufReader::new(reader).read_to_end(&mut bytes)?;
+        let header: Header = bincode::deserialize(&bytes).map_err(invalid)?;
+        if header.version != SNAPSHOT_VERSION {
+            return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                      format!("snapshot version {} is not supported (expected {})",
+                                              header.version,
+                                              SNAPSHOT_VERSION)));
+        }
+        bincode::deserialize(&bytes).map_err(invalid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Snapshot;
+    use config::SimConfig;
+    use replay::TickDigest;
+    use world::World;
+    use SEED;
+
+    #[test]
+    fn restored_world_continues_exactly() {
+        // Spawn bots early so that their brains are part of what has to survive the round trip
+        let mut config = SimConfig::default();
+        config.start_spawning_at = 10;
+        let mut world: World = World::new(&SEED, config);
+        for _ in 0..300 {
+            world.step();
+        }
+
+        let mut bytes = Vec::new();
+        Snapshot::new(&world).write_to(&mut bytes).unwrap();
+        let snapshot: Snapshot = Snapshot::read_from(&bytes[..]).unwrap();
+        let mut restored: World = snapshot.restore();
+
+        for _ in 0..300 {
+            assert_eq!(TickDigest::step(&mut restored), TickDigest::step(&mut world));
+        }
+    }
+}
@@ -0,0 +1,238 @@
+use std::collections::HashMap;
+
+use na::Norm;
+
+use super::Vec3;
+
+/// Points bucketed into cubes so that the pairs of points close to each other can be found without checking every
+/// pair.
+pub struct Grid {
+    cell: f64,
+    cells: HashMap<[i64; 3], Vec<usize>>,
+}
+
+impl Grid {
+    /// Bucket `positions` into cubes `cell` wide, which must be positive.
+    pub fn new(positions: &[Vec3], cell: f64) -> Self {
+        let mut grid = Grid {
+            cell: cell,
+            cells: HashMap::new(),
+        };
+        for (i, &p) in positions.iter().enumerate() {
+            let key = grid.key(p);
+            grid.cells.entry(key).or_insert_with(Vec::new).push(i);
+        }
+        grid
+    }
+
+    fn key(&self, p: Vec3) -> [i64; 3] {
+        [(p.x / self.cell).floor() as i64, (p.y / self.cell).floor() as i64, (p.z / self.cell).floor() as i64]
+    }
+
+    /// Every pair of indices `(i, j)` with `i < j` whose positions are less than the cell width apart, in order.
+    ///
+    /// The distance is measured straight across the space rather than around its wrap, which is how the simulation
+    /// has always measured the distances it uses for connecting and pulling nodes. Measuring around the wrap would
+    /// connect and pull nodes near opposite faces of the space that never were before, so runs would change. The
+    /// repulsion, which has always been measured around the wrap, is approximated by `Octree` instead.
+    pub fn pairs(&self, positions: &[Vec3]) -> Vec<(usize, usize)> {
+        let limit = self.cell * self.cell;
+        let mut pairs = Vec::new();
+        for (key, members) in &self.cells {
+            for dx in -1..2 {
+                for dy in -1..2 {
+                    for dz in -1..2 {
+                        let other = [key[0] + dx, key[1] + dy, key[2] + dz];
+                        if let Some(others) = self.cells.get(&other) {
+                            for &i in members {
+                                for &j in others {
+                                    if i < j && (positions[i] - positions[j]).sqnorm() < limit {
+                                        pairs.push((i, j));
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        pairs.sort();
+        pairs
+    }
+}
+
+// Octants with this many points or fewer aren't divided any further
+const LEAF_SIZE: usize = 8;
+// Points closer together than this many halvings of the space all end up in the same leaf
+const MAX_DEPTH: usize = 24;
+
+struct Octant {
+    size: f64,
+    centroid: Vec3,
+    count: usize,
+    parent: Option<usize>,
+    // The points in a leaf or the octants a branch is divided into
+    points: Vec<usize>,
+    children: Vec<usize>,
+}
+
+/// An octree of points in a cube, used to approximate the forces of distant groups of points with a single force
+/// from their centroid (the Barnes-Hut approximation).
+pub struct Octree {
+    octants: Vec<Octant>,
+    // The leaf each point is in
+    leaves: Vec<usize>,
+}
+
+impl Octree {
+    /// Build the tree of `positions`, all of which lie in the cube at `corner` that is `size` wide on every side.
+    pub fn new(positions: &[Vec3], corner: Vec3, size: f64) -> Self {
+        let mut tree = Octree {
+            octants: Vec::new(),
+            leaves: vec![0; positions.len()],
+        };
+        tree.build(positions, (0..positions.len()).collect(), None, corner, size, 0);
+        tree
+    }
+
+    fn build(&mut self,
+             positions: &[Vec3],
+             points: Vec<usize>,
+             parent: Option<usize>,
+             corner: Vec3,
+             size: f64,
+             depth: usize)
+             -> usize {
+        let count = points.len();
+        let sum = points.iter().fold(Vec3::new(0.0, 0.0, 0.0), |s, &i| s + positions[i]);
+        let index = self.octants.len();
+        self.octants.push(Octant {
+            size: size,
+            centroid: if count == 0 { corner } else { sum / count as f64 },
+            count: count,
+            parent: parent,
+            points: Vec::new(),
+            children: Vec::new(),
+        });
+        if count <= LEAF_SIZE || depth >= MAX_DEPTH {
+            for &i in &points {
+                self.leaves[i] = index;
+            }
+            self.octants[index].points = points;
+            return index;
+        }
+        let half = size / 2.0;
+        let mut parts = vec![Vec::new(); 8];
+        for i in points {
+            let p = positions[i];
+            let part = (p.x >= corner.x + half) as usize | ((p.y >= corner.y + half) as usize) << 1 |
+                       ((p.z >= corner.z + half) as usize) << 2;
+            parts[part].push(i);
+        }
+        let mut children = Vec::new();
+        for (part, points) in parts.into_iter().enumerate() {
+            if points.is_empty() {
+                continue;
+            }
+            let offset = Vec3::new(if part & 1 != 0 { half } else { 0.0 },
+                                   if part & 2 != 0 { half } else { 0.0 },
+                                   if part & 4 != 0 { half } else { 0.0 });
+            children.push(self.build(positions, points, Some(index), corner + offset, half, depth + 1));
+        }
+        self.octants[index].children = children;
+        index
+    }
+
+    /// Call `interact` with the position and number of points of every point or group of points that point `i` should
+    /// feel, leaving out `i` itself. A group is used in place of its points when its width is less than `theta` times
+    /// its distance from `i`, so a `theta` of 0 visits every point. `delta` gives the vector from one position to
+    /// another, which lets the tree be used in a space that wraps around.
+    pub fn interactions<D, F>(&self, positions: &[Vec3], i: usize, theta: f64, delta: D, mut interact: F)
+        where D: Fn(Vec3, Vec3) -> Vec3,
+              F: FnMut(Vec3, usize)
+    {
+        let p = positions[i];
+        // The octants that hold `i`, which must never stand in for their points
+        let mut holding = vec![self.leaves[i]];
+        while let Some(parent) = self.octants[*holding.last().unwrap()].parent {
+            holding.push(parent);
+        }
+        let mut stack = vec![0];
+        while let Some(o) = stack.pop() {
+            let octant = &self.octants[o];
+            if octant.count == 0 {
+                continue;
+            }
+            if octant.children.is_empty() {
+                for &j in &octant.points {
+                    if j != i {
+                        interact(positions[j], 1);
+                    }
+                }
+            } else if !holding.contains(&o) && octant.size < theta * delta(p, octant.centroid).norm() {
+                interact(octant.centroid, octant.count);
+            } else {
+                stack.extend(octant.children.iter().cloned());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use na::Norm;
+    use rand::Rng;
+
+    use super::{Grid, Octree};
+    use rng::SimRng;
+    use Vec3;
+
+    fn random_positions(rng: &mut SimRng, count: usize, low: f64, high: f64) -> Vec<Vec3> {
+        (0..count)
+            .map(|_| Vec3::new(rng.gen_range(low, high), rng.gen_range(low, high), rng.gen_range(low, high)))
+            .collect()
+    }
+
+    #[test]
+    fn grid_pairs_match_brute_force() {
+        let cell = 10.0;
+        let mut rng = SimRng::new(&[20]);
+        let mut positions = random_positions(&mut rng, 400, -100.0, 100.0);
+        // Points exactly on cell boundaries, some exactly a cell width apart, which isn't close enough to pair
+        for &x in &[-20.0, -10.0, 0.0, 10.0, 20.0] {
+            positions.push(Vec3::new(x, 0.0, 0.0));
+            positions.push(Vec3::new(x, 10.0, -10.0));
+            positions.push(Vec3::new(x + 9.999, 0.0, 0.0));
+        }
+        let mut expected = Vec::new();
+        for i in 0..positions.len() {
+            for j in i + 1..positions.len() {
+                if (positions[i] - positions[j]).sqnorm() < cell * cell {
+                    expected.push((i, j));
+                }
+            }
+        }
+        assert_eq!(Grid::new(&positions, cell).pairs(&positions), expected);
+    }
+
+    #[test]
+    fn octree_without_approximation_visits_every_other_point_once() {
+        let mut rng = SimRng::new(&[20]);
+        let mut positions = random_positions(&mut rng, 300, 0.0, 1000.0);
+        // Points on the boundaries between octants
+        positions.push(Vec3::new(500.0, 500.0, 500.0));
+        positions.push(Vec3::new(250.0, 500.0, 750.0));
+        positions.push(Vec3::new(0.0, 0.0, 0.0));
+        let tree = Octree::new(&positions, Vec3::new(0.0, 0.0, 0.0), 1000.0);
+        for i in 0..positions.len() {
+            let mut visits = vec![0; positions.len()];
+            tree.interactions(&positions, i, 0.0, |a, b| b - a, |p, count| {
+                assert_eq!(count, 1);
+                visits[positions.iter().position(|&q| q == p).unwrap()] += 1;
+            });
+            for (j, &v) in visits.iter().enumerate() {
+                assert_eq!(v, if j == i { 0 } else { 1 });
+            }
+        }
+    }
+}
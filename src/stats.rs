@@ -0,0 +1,417 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use serde_json;
+
+use analysis;
+use brain::Brain;
+use metrics::{self, GraphMetrics, DEGREE_BUCKETS};
+use world::World;
+
+/// The number of buckets in the bots per node histogram. Bucket 0 counts empty nodes, bucket `k` counts nodes with
+/// `2^(k-1)` to `2^k - 1` bots, and the last bucket counts every node with more than that.
+pub const HISTOGRAM_BUCKETS: usize = 8;
+
+fn bucket(bots: usize) -> usize {
+    let mut b = 0;
+    let mut n = bots;
+    while n != 0 && b < HISTOGRAM_BUCKETS - 1 {
+        n >>= 1;
+        b += 1;
+    }
+    b
+}
+
+fn bucket_label(b: usize) -> String {
+    match b {
+        0 => "bots_0".to_string(),
+        1 => "bots_1".to_string(),
+        b if b == HISTOGRAM_BUCKETS - 1 => format!("bots_{}_up", 1 << (b - 1)),
+        b => format!("bots_{}_{}", 1 << (b - 1), (1 << b) - 1),
+    }
+}
+
+/// The header row of the CSV format, without its line ending.
+fn csv_header() -> String {
+    let mut header = "period,nodes,edges,bots,node_energy,bot_energy,divide_births,mate_births,deaths,moves,\
+                      total_divide_births,total_mate_births,total_deaths,total_moves,mean_connections,resets,\
+                      node_brain_effective_length,bot_brain_effective_length,\
+                      final_brain_effective_length,node_brain_inputs_read,bot_brain_inputs_read,\
+                      final_brain_inputs_read,components,largest_component,clustering,diameter"
+        .to_string();
+    for b in 0..HISTOGRAM_BUCKETS {
+        header.push(',');
+        header.push_str(&bucket_label(b));
+    }
+    for b in 0..DEGREE_BUCKETS {
+        header.push(',');
+        header.push_str(&metrics::degree_label(b));
+    }
+    header
+}
+
+/// Statistics about the world at one tick.
+///
+/// In the samples written by `Statistics`, births, deaths and moves count everything that happened since the previous
+/// sample, and the totals count everything since the run began.
+#[derive(Clone, Debug, Serialize)]
+pub struct Sample {
+    pub period: u64,
+    pub nodes: usize,
+    pub edges: usize,
+    pub bots: usize,
+    pub node_energy: i64,
+    pub bot_energy: i64,
+    pub divide_births: u64,
+    pub mate_births: u64,
+    pub deaths: i64,
+    pub moves: i64,
+    pub total_divide_births: u64,
+    pub total_mate_births: u64,
+    pub total_deaths: i64,
+    pub total_moves: i64,
+    pub mean_connections: f64,
+    pub resets: i64,
+    pub bots_per_node: [u64; HISTOGRAM_BUCKETS],
+    /// The mean number of instructions that contribute to an output in the node, bot and final brains.
+    pub effective_length: [f64; 3],
+    /// The mean number of different inputs read by the node, bot and final brains.
+    pub inputs_read: [f64; 3],
+    /// The number of nodes with each number of connections, as described by `DEGREE_BUCKETS`.
+    pub degrees: [u64; DEGREE_BUCKETS],
+    pub components: usize,
+    pub largest_component: usize,
+    pub clustering: f64,
+    pub diameter: usize,
+}
+
+impl Sample {
+    /// Take a sample of the world as it is now. Births, deaths and moves all count everything since the run began.
+    pub fn new<B: Brain>(world: &World<B>) -> Self {
+        let graph = GraphMetrics::new(&world.deps);
+        let mut sample = Sample {
+            period: world.period,
+            nodes: world.deps.node_count(),
+            edges: world.deps.edge_count(),
+            bots: 0,
+            node_energy: 0,
+            bot_energy: 0,
+            divide_births: world.divide_births,
+            mate_births: world.mate_births,
+            deaths: world.deaths,
+            moves: world.moves,
+            total_divide_births: world.divide_births,
+            total_mate_births: world.mate_births,
+            total_deaths: world.deaths,
+            total_moves: world.moves,
+            mean_connections: 0.0,
+            resets: world.resets,
+            bots_per_node: [0; HISTOGRAM_BUCKETS],
+            effective_length: [0.0; 3],
+            inputs_read: [0.0; 3],
+            degrees: graph.degrees,
+            components: graph.components,
+            largest_component: graph.largest_component,
+            clustering: graph.clustering,
+            diameter: graph.diameter,
+        };
+        let mut connections = 0;
+        for n in world.deps.node_weights() {
+            sample.bots += n.bots.len();
+            sample.node_energy = sample.node_energy.saturating_add(n.energy);
+            for b in &n.bots {
+                sample.bot_energy = sample.bot_energy.saturating_add(b.energy);
+                for (k, a) in analysis::analyze_bot(b).iter().enumerate() {
+                    sample.effective_length[k] += a.effective_length() as f64;
+                    sample.inputs_read[k] += a.inputs_read_count() as f64;
+                }
+            }
+            sample.bots_per_node[bucket(n.bots.len())] += 1;
+            connections += n.connections;
+        }
+        if sample.nodes != 0 {
+            sample.mean_connections = connections as f64 / sample.nodes as f64;
+        }
+        if sample.bots != 0 {
+            for k in 0..3 {
+                sample.effective_length[k] /= sample.bots as f64;
+                sample.inputs_read[k] /= sample.bots as f64;
+            }
+        }
+        sample
+    }
+
+    fn write_csv<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        write!(writer,
+               "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+               self.period,
+               self.nodes,
+               self.edges,
+               self.bots,
+               self.node_energy,
+               self.bot_energy,
+               self.divide_births,
+               self.mate_births,
+               self.deaths,
+               self.moves,
+               self.total_divide_births,
+               self.total_mate_births,
+               self.total_deaths,
+               self.total_moves,
+               self.mean_connections,
+               self.resets,
+               self.effective_length[0],
+               self.effective_length[1],
+               self.effective_length[2],
+               self.inputs_read[0],
+               self.inputs_read[1],
+               self.inputs_read[2],
+               self.components,
+               self.largest_component,
+               self.clustering,
+               self.diameter)?;
+        for count in self.bots_per_node.iter().chain(self.degrees.iter()) {
+            write!(writer, ",{}", count)?;
+        }
+        writeln!(writer, "")
+    }
+
+    fn write_jsonl<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        serde_json::to_writer(&mut *writer, self).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        writeln!(writer, "")
+    }
+}
+
+/// The file formats statistics can be written in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StatsFormat {
+    /// Comma separated values with a header row.
+    Csv,
+    /// One JSON object per line.
+    Jsonl,
+}
+
+impl StatsFormat {
+    pub fn extension(&self) -> &'static str {
+        match *self {
+            StatsFormat::Csv => "csv",
+            StatsFormat::Jsonl => "jsonl",
+        }
+    }
+}
+
+impl FromStr for StatsFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "csv" => Ok(StatsFormat::Csv),
+            "jsonl" => Ok(StatsFormat::Jsonl),
+            _ => Err(format!("unknown statistics format {}", s)),
+        }
+    }
+}
+
+/// Writes a sample of the world every few ticks.
+pub struct Statistics {
+    writer: BufWriter<File>,
+    format: StatsFormat,
+    every: u64,
+    // The totals at the last sample, which the counts of the next sample start from
+    last: Totals,
+}
+
+/// Births, deaths and moves since the run began.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Totals {
+    divide_births: u64,
+    mate_births: u64,
+    deaths: i64,
+    moves: i64,
+}
+
+impl Totals {
+    fn of<B: Brain>(world: &World<B>) -> Self {
+        Totals {
+            divide_births: world.divide_births,
+            mate_births: world.mate_births,
+            deaths: world.deaths,
+            moves: world.moves,
+        }
+    }
+
+    /// Read the period and totals of a row, or `None` if the row was cut short.
+    fn parse(format: StatsFormat, line: &str) -> Option<(u64, Self)> {
+        match format {
+            StatsFormat::Csv => {
+                let fields = line.split(',').collect::<Vec<_>>();
+                if fields.len() != csv_header().split(',').count() {
+                    return None;
+                }
+                // The totals follow the births, deaths and moves since the previous sample
+                let totals = Totals {
+                    divide_births: fields[10].parse().ok()?,
+                    mate_births: fields[11].parse().ok()?,
+                    deaths: fields[12].parse().ok()?,
+                    moves: fields[13].parse().ok()?,
+                };
+                Some((fields[0].parse().ok()?, totals))
+            }
+            StatsFormat::Jsonl => {
+                let v = serde_json::from_str::<serde_json::Value>(line).ok()?;
+                let totals = Totals {
+                    divide_births: v["total_divide_births"].as_u64()?,
+                    mate_births: v["total_mate_births"].as_u64()?,
+                    deaths: v["total_deaths"].as_i64()?,
+                    moves: v["total_moves"].as_i64()?,
+                };
+                Some((v["period"].as_u64()?, totals))
+            }
+        }
+    }
+}
+
+impl Statistics {
+    /// Start writing statistics of `world` to `path` every `every` ticks, replacing any file already there.
+    pub fn create<P: AsRef<Path>, B: Brain>(path: P,
+                                            format: StatsFormat,
+                                            every: u64,
+                                            world: &World<B>)
+                                            -> io::Result<Self> {
+        Statistics::start(path, format, every, Vec::new(), Totals::of(world))
+    }
+
+    /// Continue the statistics at `path` of a run resumed from `world`. Samples the run wrote after the tick it is
+    /// resumed at are dropped, since those ticks will happen again, and the next sample counts from the totals of the
+    /// last sample kept so that it is the same as if the run had never stopped. A CSV file written with different
+    /// columns is an error rather than being mixed with the new rows.
+    ///
+    /// Without a file to continue, samples count from the tick the run is resumed at.
+    pub fn resume<P: AsRef<Path>, B: Brain>(path: P,
+                                            format: StatsFormat,
+                                            every: u64,
+                                            world: &World<B>)
+                                            -> io::Result<Self> {
+        let path = path.as_ref();
+        let mut kept = Vec::new();
+        let mut last = Totals::of(world);
+        if path.exists() {
+            // No sample kept means that the run is resumed before its first sample, which counts from the start
+            last = Totals::default();
+            let mut lines = BufReader::new(File::open(path)?).lines();
+            if format == StatsFormat::Csv {
+                if let Some(header) = lines.next() {
+                    let header = header?;
+                    if header != csv_header() {
+                        return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                                  "the file was written with different columns"));
+                    }
+                }
+            }
+            for line in lines {
+                let line = line?;
+                // A line cut short by a crash doesn't parse and is dropped with the rest
+                match Totals::parse(format, &line) {
+                    Some((period, totals)) if period <= world.period => {
+                        last = totals;
+                        kept.push(line);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Statistics::start(path, format, every, kept, last)
+    }
+
+    fn start<P: AsRef<Path>>(path: P,
+                             format: StatsFormat,
+                             every: u64,
+                             kept: Vec<String>,
+                             last: Totals)
+                             -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        if format == StatsFormat::Csv {
+            writeln!(writer, "{}", csv_header())?;
+        }
+        for line in &kept {
+            writeln!(writer, "{}", line)?;
+        }
+        writer.flush()?;
+        Ok(Statistics {
+            writer: writer,
+            format: format,
+            every: every,
+            last: last,
+        })
+    }
+
+    /// Write a sample if the tick that just ran is one that should be sampled.
+    pub fn tick<B: Brain>(&mut self, world: &World<B>) -> io::Result<()> {
+        if self.every == 0 || world.period % self.every != 0 {
+            return Ok(());
+        }
+
+        let mut sample = Sample::new(world);
+        sample.divide_births -= self.last.divide_births;
+        sample.mate_births -= self.last.mate_births;
+        sample.deaths -= self.last.deaths;
+        sample.moves -= self.last.moves;
+        self.last = Totals::of(world);
+
+        match self.format {
+            StatsFormat::Csv => sample.write_csv(&mut self.writer)?,
+            StatsFormat::Jsonl => sample.write_jsonl(&mut self.writer)?,
+        }
+        // Flush every sample so that the file can be watched while the run goes on
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    use super::{Statistics, StatsFormat};
+    use config::SimConfig;
+    use snapshot::Snapshot;
+    use world::World;
+    use SEED;
+
+    fn run(world: &mut World, stats: &mut Statistics, ticks: u64) {
+        for _ in 0..ticks {
+            world.step();
+            stats.tick(world).unwrap();
+        }
+    }
+
+    #[test]
+    fn resumed_statistics_match_an_uninterrupted_run() {
+        for &format in &[StatsFormat::Csv, StatsFormat::Jsonl] {
+            let dir = env::temp_dir().join(format!("evobots-stats-{}-{}", process::id(), format.extension()));
+            fs::create_dir_all(&dir).unwrap();
+            let (whole, resumed) = (dir.join("whole"), dir.join("resumed"));
+
+            let mut config = SimConfig::default();
+            config.start_spawning_at = 10;
+            let mut world: World = World::new(&SEED, config);
+            let mut stats = Statistics::create(&whole, format, 20, &world).unwrap();
+            // The snapshot is taken between samples, and the interrupted run writes a few more before it stops
+            run(&mut world, &mut stats, 150);
+            let snapshot: Snapshot = Snapshot::new(&world);
+            run(&mut world, &mut stats, 50);
+            fs::copy(&whole, &resumed).unwrap();
+            run(&mut world, &mut stats, 100);
+
+            let mut restored: World = snapshot.restore();
+            let mut resumed_stats = Statistics::resume(&resumed, format, 20, &restored).unwrap();
+            run(&mut restored, &mut resumed_stats, 150);
+
+            assert_eq!(fs::read_to_string(&resumed).unwrap(), fs::read_to_string(&whole).unwrap());
+            fs::remove_dir_all(&dir).unwrap();
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use std::fmt::Write as FmtWrite;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use zoom::Toroid;
+
+use bot::Brain;
+use frames;
+use world::World;
+use super::Vec3;
+
+// Space left around the drawing in the units of the drawing
+const MARGIN: f64 = 10.0;
+// How opaque edges are
+const EDGE_OPACITY: f64 = 0.5;
+
+/// How positions in the 3D space are flattened onto the page.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Projection {
+    /// Looking down the z axis without perspective.
+    Xy,
+    /// Looking down the y axis without perspective.
+    Xz,
+    /// Looking down the x axis without perspective.
+    Yz,
+    /// Through the camera the frames are drawn with.
+    Perspective,
+}
+
+impl Projection {
+    /// The position on the page, with y going up, and the size one unit of space has there. Positions that can't be
+    /// seen give None.
+    fn project(&self, p: Vec3) -> Option<(f64, f64, f64)> {
+        match *self {
+            Projection::Xy => Some((p.x, p.y, 1.0)),
+            Projection::Xz => Some((p.x, p.z, 1.0)),
+            Projection::Yz => Some((p.y, p.z, 1.0)),
+            // The view is only a couple of units across, so it is scaled up to keep the drawing a sensible size
+            Projection::Perspective => {
+                const SIZE: f64 = 500.0;
+                frames::perspective(p).map(|(x, y, s)| (x * SIZE, y * SIZE, s * SIZE))
+            }
+        }
+    }
+}
+
+impl FromStr for Projection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "xy" => Ok(Projection::Xy),
+            "xz" => Ok(Projection::Xz),
+            "yz" => Ok(Projection::Yz),
+            "perspective" => Ok(Projection::Perspective),
+            _ => Err(format!("unknown projection {}", s)),
+        }
+    }
+}
+
+/// An SVG color and opacity from a color with components that may be out of range.
+fn paint(color: [f32; 4]) -> (String, f32) {
+    let c = |v: f32| (v.max(0.0).min(1.0) * 255.0).round() as u8;
+    (format!("#{:02x}{:02x}{:02x}", c(color[0]), c(color[1]), c(color[2])), color[3].max(0.0).min(1.0))
+}
+
+/// The mesh of a world as an SVG image. Nodes are circles colored by `Node::color` and sized by `Node::radius`, and
+/// edges are lines in the average color of their ends. Edges that wrap around the space are left out, as they are in
+/// the viewer. With `bot_counts` every node is labeled with the number of bots in it.
+pub fn world_to_svg<B: Brain>(world: &World<B>, projection: Projection, bot_counts: bool) -> String {
+    let config = &world.config;
+    let space = config.node_space();
+    // Flip y so that up in the space is up on the page
+    let place = |p: Vec3| projection.project(p).map(|(x, y, s)| (x, -y, s));
+
+    let mut body = String::new();
+    // The corners of the box around every circle drawn
+    let mut min = (::std::f64::INFINITY, ::std::f64::INFINITY);
+    let mut max = (::std::f64::NEG_INFINITY, ::std::f64::NEG_INFINITY);
+    for e in world.deps.edge_indices() {
+        let (a, b) = world.deps.edge_endpoints(e).unwrap();
+        let (a, b) = (&world.deps[a], &world.deps[b]);
+        let rdelta = b.particle.p.position - a.particle.p.position;
+        if rdelta != space.wrap_delta(rdelta) {
+            continue;
+        }
+        if let (Some((ax, ay, _)), Some((bx, by, _))) = (place(a.particle.p.position), place(b.particle.p.position)) {
+            let (ca, cb) = (a.color(config), b.color(config));
+            let mut mix = [0.0; 4];
+            for c in 0..4 {
+                mix[c] = (ca[c] + cb[c]) / 2.0;
+            }
+            let (stroke, _) = paint(mix);
+            writeln!(body,
+                     "  <line x1=\"{:.2}\" y1=\"{:.2}\" x2=\"{:.2}\" y2=\"{:.2}\" stroke=\"{}\" \
+                      stroke-opacity=\"{}\"/>",
+                     ax,
+                     ay,
+                     bx,
+                     by,
+                     stroke,
+                     EDGE_OPACITY)
+                .unwrap();
+        }
+    }
+    for n in world.deps.node_weights() {
+        if let Some((x, y, s)) = place(n.particle.p.position) {
+            let r = n.radius() as f64 * s;
+            min = (min.0.min(x - r), min.1.min(y - r));
+            max = (max.0.max(x + r), max.1.max(y + r));
+            let (fill, opacity) = paint(n.color(config));
+            writeln!(body,
+                     "  <circle cx=\"{:.2}\" cy=\"{:.2}\" r=\"{:.2}\" fill=\"{}\" fill-opacity=\"{}\"/>",
+                     x,
+                     y,
+                     r,
+                     fill,
+                     opacity)
+                .unwrap();
+            if bot_counts {
+                writeln!(body,
+                         "  <text x=\"{:.2}\" y=\"{:.2}\" font-size=\"{:.2}\" text-anchor=\"middle\" \
+                          dominant-baseline=\"central\">{}</text>",
+                         x,
+                         y,
+                         r,
+                         n.bots.len())
+                    .unwrap();
+            }
+        }
+    }
+    // An empty world still makes a valid image
+    if min.0 > max.0 {
+        min = (0.0, 0.0);
+        max = (0.0, 0.0);
+    }
+
+    let mut out = String::new();
+    writeln!(out, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>").unwrap();
+    writeln!(out,
+             "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{:.2} {:.2} {:.2} {:.2}\">",
+             min.0 - MARGIN,
+             min.1 - MARGIN,
+             max.0 - min.0 + 2.0 * MARGIN,
+             max.1 - min.1 + 2.0 * MARGIN)
+        .unwrap();
+    writeln!(out, "  <title>tick {}</title>", world.period).unwrap();
+    out.push_str(&body);
+    writeln!(out, "</svg>").unwrap();
+    out
+}
+
+pub fn save_svg<B: Brain, P: AsRef<Path>>(world: &World<B>,
+                                          projection: Projection,
+                                          bot_counts: bool,
+                                          path: P)
+                                          -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(world_to_svg(world, projection, bot_counts).as_bytes())?;
+    writer.flush()
+}
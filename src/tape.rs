@@ -0,0 +1,117 @@
+use std::cell::RefCell;
+
+use analysis::BrainAnalysis;
+use ins::{self, Arithmetic, Ins};
+
+/// One step of a tape: `slots.push(ins(slots[a], slots[b]))`.
+#[derive(Clone, Copy, Debug)]
+struct Step {
+    ins: Ins,
+    a: u32,
+    b: u32,
+}
+
+/// A multi expression program compiled for fast evaluation.
+///
+/// Only the instructions that contribute to an output are kept. Inputs and results share one array of slots, inputs
+/// first, so every operand is a plain index and evaluating an instruction doesn't need to check what it reads.
+#[derive(Clone, Debug)]
+pub struct Tape {
+    steps: Vec<Step>,
+    inputs: usize,
+    // The slot holding each output
+    outputs: Vec<u32>,
+}
+
+thread_local! {
+    // Reused between evaluations so that computing a brain doesn't allocate
+    static SLOTS: RefCell<Vec<i64>> = RefCell::new(Vec::new());
+}
+
+impl Tape {
+    /// Compile a program with `inputs` inputs whose last `outputs` instructions are the outputs.
+    pub fn compile(program: &[(Ins, usize, usize)], inputs: usize, outputs: usize) -> Self {
+        let analysis = BrainAnalysis::of_program(program, inputs, outputs);
+        // The slot each instruction's result ends up in, for the instructions that are kept
+        let mut slot = vec![0u32; program.len()];
+        let mut steps = Vec::with_capacity(analysis.effective_length());
+        let operand = |slot: &[u32], o: usize| if o < inputs { o as u32 } else { slot[o - inputs] };
+        for (i, &(ins, a, b)) in program.iter().enumerate() {
+            if !analysis.used[i] {
+                continue;
+            }
+            let a = operand(&slot, a);
+            // Operands an instruction ignores may point at removed instructions, so they read the first operand instead
+            let b = if ins.operands() > 1 { operand(&slot, b) } else { a };
+            steps.push(Step { ins: ins, a: a, b: b });
+            slot[i] = (inputs + steps.len() - 1) as u32;
+        }
+        Tape {
+            outputs: (program.len().saturating_sub(outputs)..program.len()).map(|i| slot[i]).collect(),
+            steps: steps,
+            inputs: inputs,
+        }
+    }
+
+    /// The number of instructions left after removing those that don't contribute to an output.
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    /// Run the tape, giving the same outputs as the program it was compiled from. Outputs past the end of a program
+    /// shorter than its number of outputs are left alone.
+    pub fn compute(&self, mode: Arithmetic, inputs: &[i64], outputs: &mut [i64]) {
+        SLOTS.with(|slots| {
+            let mut slots = slots.borrow_mut();
+            slots.clear();
+            slots.extend_from_slice(&inputs[..self.inputs]);
+            for s in &self.steps {
+                let v = ins::evaluate(mode, &s.ins, slots[s.a as usize], slots[s.b as usize]);
+                slots.push(v);
+            }
+            for (o, &s) in outputs.iter_mut().zip(self.outputs.iter()) {
+                *o = slots[s as usize];
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use brain::{Brain, BrainShape};
+    use ins::{Arithmetic, OpcodeTable};
+    use mep::MepBrain;
+    use rng::SimRng;
+
+    #[test]
+    fn tape_matches_interpreted_program() {
+        let mut rng = SimRng::new(&[18]);
+        let opcodes = OpcodeTable::default();
+        // Programs shorter than their number of outputs leave the outputs past their end alone
+        for &instructions in &[1, 3, 8, 64] {
+            let shape = BrainShape {
+                inputs: 6,
+                outputs: 5,
+                instructions: instructions,
+                mutate_size: 4,
+                crossover_points: 2,
+            };
+            for _ in 0..50 {
+                let mut brain = MepBrain::random(&shape, &opcodes, &mut rng);
+                brain.mutate(&opcodes, &mut rng);
+                for &mode in &[Arithmetic::Wrapping, Arithmetic::Saturating, Arithmetic::Checked] {
+                    let inputs = (0..shape.inputs)
+                        .map(|_| rng.gen::<i64>() >> rng.gen_range(0, 64))
+                        .collect::<Vec<i64>>();
+                    let mut expected = vec![7; shape.outputs];
+                    let mut actual = vec![7; shape.outputs];
+                    brain.interpret(mode, &inputs, &mut expected);
+                    brain.compute(mode, &inputs, &mut actual);
+                    assert_eq!(actual, expected);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,294 @@
+use gg;
+use glium;
+use na;
+use zoom;
+use crossbeam;
+use itertools::*;
+
+use na::{ToHomogeneous, Translation, Rotation};
+
+use evobots::{World, Vec3};
+
+const STARTING_POSITION: f32 = 1000.0;
+const MOVE_SPEED: f32 = 5.0;
+const ROTATION_RATE: f32 = 0.005;
+// Fast-forwarding can't go faster than this many ticks per frame
+const MAX_TICKS_PER_FRAME: u64 = 1 << 16;
+
+const EDGE_FALLOFF: f32 = 0.05;
+const NODE_FALLOFF: f32 = 0.25;
+
+fn vec_to_spos(v: Vec3) -> [f32; 3] {
+    match v {
+        Vec3 { x, y, z } => [x as f32, y as f32, z as f32],
+    }
+}
+
+/// Open a fullscreen window and run the simulation while rendering it until the window is closed or the simulation
+/// reaches tick `ticks` (if given). `step` is called to advance the world by one tick and stops the viewer when it
+/// returns false. The world is handed back when the viewer stops.
+///
+/// The simulation advances `ticks_per_frame` ticks (at most `MAX_TICKS_PER_FRAME`) while each frame is drawn, and the
+/// next frame waits for those ticks to finish. With `vsync` frames also wait for the display, so the simulation runs
+/// at most `ticks_per_frame` ticks per refresh of the display; without it frames are drawn as soon as the ticks are
+/// done. Besides moving the camera, these keys control the simulation:
+///
+/// * Space pauses and resumes it.
+/// * Period advances it by a single tick while paused.
+/// * Plus and minus double and halve the number of ticks per frame.
+pub fn run<F>(mut world: World, ticks: Option<u64>, ticks_per_frame: u64, vsync: bool, mut step: F) -> World
+    where F: FnMut(&mut World) -> bool + Send
+{
+    use glium::DisplayBuild;
+
+    let mut builder = glium::glutin::WindowBuilder::new()
+        .with_fullscreen(glium::glutin::get_available_monitors().next().unwrap());
+    if vsync {
+        builder = builder.with_vsync();
+    }
+    let display = builder.build_glium().unwrap();
+    let window = display.get_window().unwrap();
+    // match window.set_cursor_state(glium::glutin::CursorState::Hide) {
+    // Ok(_) => {},
+    // Err(_) => println!("Cursor hide not available on this platform; starting without it."),
+    // }
+    let glowy = gg::Renderer::new(&display);
+    let mut focus_state = true;
+
+    let mut print_info = false;
+
+    let mut paused = false;
+    let mut single_step = false;
+    let mut ticks_per_frame = ::std::cmp::min(ticks_per_frame, MAX_TICKS_PER_FRAME);
+
+    // Set mouse cursor to middle
+    {
+        let (dimx, dimy) = display.get_framebuffer_dimensions();
+        let (hdimx, hdimy) = (dimx / 2, dimy / 2);
+        window.set_cursor_position(hdimx as i32, hdimy as i32).ok().unwrap();
+    }
+
+    let mut movement = na::Iso3::<f32>::new(na::Vec3::new(0.0, 0.0, STARTING_POSITION),
+                                            na::Vec3::new(0.0, 0.0, 0.0));
+
+    let mut upstate = glium::glutin::ElementState::Released;
+    let mut dnstate = glium::glutin::ElementState::Released;
+    let mut ltstate = glium::glutin::ElementState::Released;
+    let mut rtstate = glium::glutin::ElementState::Released;
+    let mut fdstate = glium::glutin::ElementState::Released;
+    let mut bkstate = glium::glutin::ElementState::Released;
+
+    loop {
+        use glium::Surface;
+
+        if let Some(t) = ticks {
+            if world.period >= t {
+                break;
+            }
+        }
+
+        let mut closed = false;
+        let mut running = true;
+
+        let (deps, config) = (&mut world.deps, &world.config);
+        let node_vec = deps.node_weights_mut()
+            .map(|n| {
+                gg::Node {
+                    position: vec_to_spos(n.particle.p.position),
+                    color: n.color(config),
+                    falloff: NODE_FALLOFF,
+                    radius: n.radius(),
+                }
+            })
+            .collect_vec();
+
+        let edge_vec = {
+            let node_space = config.node_space();
+            deps.edge_indices().map(|e| deps.edge_endpoints(e)).fold(Vec::new(), |mut v, n| {
+                use zoom::Toroid;
+                let indices = n.unwrap().clone();
+                let nodes = (deps.node_weight(indices.0).unwrap(),
+                             deps.node_weight(indices.1).unwrap());
+                let rdelta = nodes.1.particle.p.position - nodes.0.particle.p.position;
+                if rdelta == node_space.wrap_delta(rdelta) {
+                    v.push(gg::Node {
+                        position: vec_to_spos(nodes.0.particle.p.position),
+                        color: nodes.0.color(config),
+                        falloff: EDGE_FALLOFF,
+                        radius: nodes.0.radius(),
+                    });
+                    v.push(gg::Node {
+                        position: vec_to_spos(nodes.1.particle.p.position),
+                        color: nodes.1.color(config),
+                        falloff: EDGE_FALLOFF,
+                        radius: nodes.1.radius(),
+                    });
+                }
+                v
+            })
+        };
+
+        // A paused simulation only moves when it is stepped
+        let frame_ticks = if !paused {
+            ticks_per_frame
+        } else if single_step {
+            1
+        } else {
+            0
+        };
+        single_step = false;
+
+        world.print_info = print_info;
+        let stepping = &mut world;
+        let step = &mut step;
+        let still_running = &mut running;
+
+        crossbeam::scope(|scope| {
+            scope.spawn(move || {
+                for _ in 0..frame_ticks {
+                    if ticks.map(|t| stepping.period >= t).unwrap_or(false) {
+                        break;
+                    }
+                    if !step(stepping) {
+                        *still_running = false;
+                        break;
+                    }
+                }
+            });
+
+            {
+                // Render code
+                let mut target = display.draw();
+                target.clear_color(0.0, 0.0, 0.0, 1.0);
+
+                let matr = movement.to_homogeneous() * 3.0;
+
+                // Update perspective every frame
+                let fbdim = window.get_inner_size_pixels().unwrap();
+                let perspective =
+                    *na::Persp3::new((fbdim.0 as f32) / (fbdim.1 as f32), 1.0, 0.0, 500.0)
+                        .to_mat()
+                        .as_ref();
+
+                // Render nodes
+                glowy.render_nodes(&mut target, matr.as_ref(), &perspective, &node_vec[..]);
+
+                // Render edges
+                glowy.render_edges(&mut target, matr.as_ref(), &perspective, &edge_vec[..]);
+
+                target.finish().unwrap();
+            }
+
+            for ev in display.poll_events() {
+                match ev {
+                    glium::glutin::Event::Closed => closed = true,
+                    glium::glutin::Event::KeyboardInput(state,
+                                                        _,
+                                                        Some(glium::glutin::VirtualKeyCode::M)) => {
+                        print_info = state == glium::glutin::ElementState::Pressed;
+                    }
+                    glium::glutin::Event::KeyboardInput(glium::glutin::ElementState::Pressed,
+                                                        _,
+                                                        Some(glium::glutin::VirtualKeyCode::Space)) => {
+                        paused = !paused;
+                        println!("{}", if paused { "Paused" } else { "Resumed" });
+                    }
+                    glium::glutin::Event::KeyboardInput(glium::glutin::ElementState::Pressed,
+                                                        _,
+                                                        Some(glium::glutin::VirtualKeyCode::Period)) => {
+                        if paused {
+                            single_step = true;
+                        }
+                    }
+                    glium::glutin::Event::KeyboardInput(glium::glutin::ElementState::Pressed,
+                                                        _,
+                                                        Some(glium::glutin::VirtualKeyCode::Equals)) |
+                    glium::glutin::Event::KeyboardInput(glium::glutin::ElementState::Pressed,
+                                                        _,
+                                                        Some(glium::glutin::VirtualKeyCode::Add)) => {
+                        ticks_per_frame = ::std::cmp::min(ticks_per_frame.saturating_mul(2), MAX_TICKS_PER_FRAME);
+                        println!("{} ticks per frame", ticks_per_frame);
+                    }
+                    glium::glutin::Event::KeyboardInput(glium::glutin::ElementState::Pressed,
+                                                        _,
+                                                        Some(glium::glutin::VirtualKeyCode::Minus)) |
+                    glium::glutin::Event::KeyboardInput(glium::glutin::ElementState::Pressed,
+                                                        _,
+                                                        Some(glium::glutin::VirtualKeyCode::Subtract)) => {
+                        ticks_per_frame = ::std::cmp::max(ticks_per_frame / 2, 1);
+                        println!("{} ticks per frame", ticks_per_frame);
+                    }
+                    glium::glutin::Event::KeyboardInput(state,
+                                                        _,
+                                                        Some(glium::glutin::VirtualKeyCode::W)) => {
+                        fdstate = state;
+                    }
+                    glium::glutin::Event::KeyboardInput(state,
+                                                        _,
+                                                        Some(glium::glutin::VirtualKeyCode::S)) => {
+                        bkstate = state;
+                    }
+                    glium::glutin::Event::KeyboardInput(state,
+                                                        _,
+                                                        Some(glium::glutin::VirtualKeyCode::A)) => {
+                        ltstate = state;
+                    }
+                    glium::glutin::Event::KeyboardInput(state,
+                                                        _,
+                                                        Some(glium::glutin::VirtualKeyCode::D)) => {
+                        rtstate = state;
+                    }
+                    glium::glutin::Event::KeyboardInput(state,
+                                                        _,
+                                                        Some(glium::glutin::VirtualKeyCode::Q)) => {
+                        dnstate = state;
+                    }
+                    glium::glutin::Event::KeyboardInput(state,
+                                                        _,
+                                                        Some(glium::glutin::VirtualKeyCode::E)) => {
+                        upstate = state;
+                    }
+                    glium::glutin::Event::MouseMoved(x, y) => {
+                        let (dimx, dimy) = display.get_framebuffer_dimensions();
+                        let (hdimx, hdimy) = (dimx / 2, dimy / 2);
+                        if focus_state {
+                            movement.append_rotation_mut(&na::Vec3::new(-(y - hdimy as i32) as f32 *
+                                                                    ROTATION_RATE,
+                                                                    (x - hdimx as i32) as f32 *
+                                                                    ROTATION_RATE,
+                                                                    0.0));
+                            window.set_cursor_position(hdimx as i32, hdimy as i32).ok().unwrap();
+                        }
+                    }
+                    glium::glutin::Event::Focused(s) => {
+                        focus_state = s;
+                    }
+                    _ => (),
+                }
+            }
+
+            if upstate == glium::glutin::ElementState::Pressed {
+                movement.append_translation_mut(&na::Vec3::new(0.0, -MOVE_SPEED, 0.0));
+            }
+            if dnstate == glium::glutin::ElementState::Pressed {
+                movement.append_translation_mut(&na::Vec3::new(0.0, MOVE_SPEED, 0.0));
+            }
+            if ltstate == glium::glutin::ElementState::Pressed {
+                movement.append_translation_mut(&na::Vec3::new(-MOVE_SPEED, 0.0, 0.0));
+            }
+            if rtstate == glium::glutin::ElementState::Pressed {
+                movement.append_translation_mut(&na::Vec3::new(MOVE_SPEED, 0.0, 0.0));
+            }
+            if fdstate == glium::glutin::ElementState::Pressed {
+                movement.append_translation_mut(&na::Vec3::new(0.0, 0.0, -MOVE_SPEED));
+            }
+            if bkstate == glium::glutin::ElementState::Pressed {
+                movement.append_translation_mut(&na::Vec3::new(0.0, 0.0, MOVE_SPEED));
+            }
+        });
+        if closed || !running {
+            break;
+        }
+    }
+    world
+}
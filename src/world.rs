@@ -0,0 +1,828 @@
+use std::collections::{BTreeSet, BinaryHeap, HashMap};
+
+use itertools::*;
+use rand::Rng;
+use rayon::prelude::*;
+use petgraph::graph::NodeIndex;
+
+use super::*;
+use config::SimConfig;
+use ins::OpcodeTable;
+use rng::SimRng;
+use lineage::Lineage;
+use bot::*;
+use node::*;
+use rank::*;
+use space::{Grid, Octree};
+
+pub type Graph<B = MepBrain> = petgraph::Graph<Node<B>, (), petgraph::Undirected>;
+
+/// Get the neighbors of a node in index order.
+///
+/// The order of petgraph's adjacency lists depends on the whole history of edge insertions and removals, which a
+/// snapshot can't capture, so the simulation must never depend on it.
+fn sorted_neighbors<B: Brain>(deps: &Graph<B>, i: NodeIndex) -> Vec<NodeIndex> {
+    let mut neighbors = deps.neighbors(i).collect_vec();
+    neighbors.sort();
+    neighbors
+}
+
+/// Make a bot with a random brain and give it an identity.
+fn spawn_bot<B: Brain>(rng: &mut SimRng,
+                       opcodes: &OpcodeTable,
+                       config: &SimConfig,
+                       lineage: &mut Lineage,
+                       period: u64)
+                       -> Box<Bot<B>> {
+    let mut bot = Bot::new(rng, opcodes, config);
+    lineage.birth(&mut bot, period);
+    Box::new(bot)
+}
+
+/// The pairs of nodes to connect, in order: nodes whose bots chose to connect them by sharing a connect signal and
+/// nodes that are simply close enough.
+fn plan_connections<B: Brain>(deps: &Graph<B>, config: &SimConfig) -> Vec<(usize, usize)> {
+    use zoom::Position;
+    use na::Norm;
+    let nodes = deps.raw_nodes();
+    let positions = nodes.iter().map(|n| n.weight.particle.position()).collect_vec();
+    let mut plans = BTreeSet::new();
+
+    //Group the nodes by the connect signals of their bots so that only nodes sharing a signal are compared
+    let mut signals = HashMap::new();
+    for (i, n) in nodes.iter().enumerate() {
+        for b in &n.weight.bots {
+            if b.connect_signal.abs() >= config.connect_signal_min {
+                let v = signals.entry(b.connect_signal).or_insert_with(Vec::new);
+                if v.last() != Some(&i) {
+                    v.push(i);
+                }
+            }
+        }
+    }
+    let limit = config.bot_choice_connect_length * config.bot_choice_connect_length;
+    for sharing in signals.values() {
+        for (k, &i) in sharing.iter().enumerate() {
+            for &j in &sharing[k + 1..] {
+                if (positions[i] - positions[j]).sqnorm() < limit {
+                    plans.insert((i, j));
+                }
+            }
+        }
+    }
+
+    if config.connect_after > 0.0 {
+        plans.extend(Grid::new(&positions, config.connect_after).pairs(&positions));
+    }
+    plans.into_iter().collect()
+}
+
+/// What a bot decided in the bots phase, before anything it decided is acted on.
+struct Thought {
+    decision: Decision,
+    memory: [i64; finalbrain::TOTAL_MEMORY],
+}
+
+/// Run the brains of every bot in node `i` without changing anything. `neighbors` has the node itself first and then
+/// its neighbors in index order, and `rngval` is the random input the bots in the node share.
+///
+/// Nothing a bot decides is acted on until every node has thought, so nodes can think in any order or in parallel.
+/// Nothing random is drawn while thinking; `rngval` is drawn beforehand as `World::update_bots` describes.
+fn think<B: Brain>(deps: &Graph<B>,
+                   i: NodeIndex,
+                   neighbors: &[NodeIndex],
+                   rngval: i64,
+                   period: u64,
+                   config: &SimConfig)
+                   -> Vec<Thought> {
+    //Make arrays for bot brain inputs
+    let mut node_inputs = [0i64; nodebrain::TOTAL_INPUTS];
+    let mut bot_inputs = [0i64; botbrain::TOTAL_INPUTS];
+    let mut final_inputs = [0i64; finalbrain::TOTAL_INPUTS];
+
+    //Make the static values
+    let statics = [0, 1, 2, -1];
+    //Assign static values to each of the input arrays
+    node_inputs.iter_mut().set_from(statics.iter().cloned());
+    bot_inputs.iter_mut().set_from(statics.iter().cloned());
+    final_inputs.iter_mut().set_from(statics.iter().cloned());
+    node_inputs[nodebrain::PERIOD] = period as i64;
+    bot_inputs[botbrain::PERIOD] = period as i64;
+    final_inputs[finalbrain::PERIOD] = period as i64;
+
+    node_inputs[nodebrain::RAND] = rngval;
+    bot_inputs[botbrain::RAND] = rngval;
+    final_inputs[finalbrain::RAND] = rngval;
+
+    let ref pnode = deps[i];
+
+    // Set node inputs
+    node_inputs[nodebrain::PRESENT_NODE_ENERGY] = pnode.energy;
+    node_inputs[nodebrain::PRESENT_NODE_BOTS] = pnode.bots.len() as i64;
+    node_inputs[nodebrain::PRESENT_NODE_CONNECTIONS] = pnode.connections;
+    node_inputs[nodebrain::FORCE] = (pnode.oldforce * config.force_input_scalar) as i64;
+    bot_inputs[botbrain::PRESENT_NODE_ENERGY] = pnode.energy;
+    bot_inputs[botbrain::PRESENT_NODE_BOTS] = pnode.bots.len() as i64;
+    bot_inputs[botbrain::PRESENT_NODE_CONNECTIONS] = pnode.connections;
+    bot_inputs[botbrain::FORCE] = (pnode.oldforce * config.force_input_scalar) as i64;
+    final_inputs[finalbrain::PRESENT_NODE_ENERGY] = pnode.energy;
+    final_inputs[finalbrain::PRESENT_NODE_BOTS] = pnode.bots.len() as i64;
+    final_inputs[finalbrain::PRESENT_NODE_CONNECTIONS] = pnode.connections;
+    final_inputs[finalbrain::FORCE] = (pnode.oldforce * config.force_input_scalar) as i64;
+
+    //Iterate through all bots (b) in the node being processed
+    pnode.bots
+        .iter()
+        .enumerate()
+        .map(|(ib, b)| {
+            //Create a BTree to rank the nodes and fill it with default nodes
+            let mut node_outputs = [0; nodebrain::TOTAL_OUTPUTS];
+            let mut bot_outputs = [0; botbrain::TOTAL_OUTPUTS];
+            let mut node_heap = BinaryHeap::from(
+                vec![Rank{rank: 0, data: [-1; nodebrain::TOTAL_OUTPUTS]}; finalbrain::TOTAL_NODE_INPUTS]
+            );
+
+            //Create a BTree to rank the nodes and fill it with default bots
+            let mut bot_heap = BinaryHeap::from(
+                vec![Rank{rank: 0, data: [-1; botbrain::TOTAL_OUTPUTS]}; finalbrain::TOTAL_BOT_INPUTS]
+            );
+
+            //Iterate through each node and produce the outputs
+            for (i, &n) in neighbors.iter().enumerate() {
+                //Get the node reference
+                let n = &deps[n];
+                //Set the inputs for the node brain
+                node_inputs[nodebrain::NODE_ENERGY] = n.energy;
+                node_inputs[nodebrain::NODE_BOTS] = n.bots.len() as i64;
+                node_inputs[nodebrain::SELF_ENERGY] = b.energy;
+                node_inputs[nodebrain::NODE_CONNECTIONS] = n.connections;
+                node_inputs[nodebrain::STATIC_INPUTS..].iter_mut().set_from(b.memory.iter().cloned());
+
+                b.node_brain.compute(config.arithmetic, &node_inputs[..], &mut node_outputs);
+
+                //The rank is replaced by the index of the node when passed on
+                let rank = Rank{
+                    rank: node_outputs[0],
+                    data: {
+                        let mut l = node_outputs;
+                        l[0] = i as i64;
+                        l
+                    },
+                };
+
+                //Add this rank to the heap
+                node_heap.push(rank);
+                //Remove the lowest rank from the heap to stay at the same amount
+                node_heap.pop();
+            }
+
+            //Iterate through each bot and produce the outputs
+            for (iob, ob) in pnode.bots.iter().enumerate() {
+                //Set the inputs for the bot brain
+                bot_inputs[botbrain::SELF_ENERGY] = b.energy;
+                bot_inputs[botbrain::BOT_ENERGY] = ob.energy;
+                bot_inputs[botbrain::BOT_SIGNAL] = ob.signal;
+                bot_inputs[botbrain::STATIC_INPUTS..].iter_mut().set_from(b.memory.iter().cloned());
+
+                b.bot_brain.compute(config.arithmetic, &bot_inputs[..], &mut bot_outputs);
+
+                //The rank is replaced by the index of the bot when passed on
+                let rank = Rank{
+                    rank: bot_outputs[0],
+                    data: {
+                        let mut l = bot_outputs;
+                        l[0] = iob as i64;
+                        l
+                    },
+                };
+
+                //Add this rank to the heap
+                bot_heap.push(rank);
+                //Remove the lowest rank from the heap to stay at the same amount
+                bot_heap.pop();
+            }
+
+            //Make the bot's final decision
+
+            //Provide static inputs
+            final_inputs[finalbrain::SELF_ENERGY] = b.energy;
+            final_inputs[finalbrain::SELF_INDEX] = ib as i64;
+            final_inputs[finalbrain::STATIC_INPUTS..].iter_mut().set_from(
+                b.memory.iter().cloned().chain(
+                    //Provide the highest ranking node inputs
+                    node_heap.iter().flat_map(|r| r.data.iter().cloned())
+                ).chain(
+                    //Provide the highest ranking bot inputs
+                    bot_heap.iter().flat_map(|r| r.data.iter().cloned())
+                )
+            );
+
+            let mut outputs = [0; finalbrain::TOTAL_OUTPUTS];
+            b.final_brain.compute(config.arithmetic, &final_inputs[..], &mut outputs);
+            let mut thought = Thought {
+                decision: Decision {
+                    mate: outputs[0],
+                    node: outputs[1],
+                    rate: outputs[2],
+                    signal: outputs[3],
+                    connect_signal: outputs[4],
+                    sever_choice: outputs[5],
+                    pull: outputs[6],
+                },
+                memory: [0; finalbrain::TOTAL_MEMORY],
+            };
+            thought.memory.copy_from_slice(&outputs[finalbrain::STATIC_OUTPUTS..]);
+            thought
+        })
+        .collect()
+}
+
+/// The phases of a tick in the order they run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+    /// Edge springs, node energy growth and spawning into empty nodes.
+    Growth,
+    /// Node splitting, bot spawning, particle movement, node obliteration and breaking of long edges.
+    Split,
+    /// Energy diffusion along edges.
+    Diffusion,
+    /// Bot brains, mating, moving and severing of edges.
+    Bots,
+    /// Forces between all nodes and new connections.
+    Connection,
+    /// Arrival of moved bots, bot upkeep, death, mutation and energy consumption.
+    Settle,
+}
+
+pub const PHASES: [Phase; 6] = [Phase::Growth, Phase::Split, Phase::Diffusion, Phase::Bots, Phase::Connection,
+                                Phase::Settle];
+
+impl Phase {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Phase::Growth => "growth",
+            Phase::Split => "split",
+            Phase::Diffusion => "diffusion",
+            Phase::Bots => "bots",
+            Phase::Connection => "connection",
+            Phase::Settle => "settle",
+        }
+    }
+}
+
+/// The whole simulated world: the node mesh with its bots, the random number generator that drives it and the tick
+/// counters. Calling `step` advances the simulation by exactly one tick. `B` is the kind of brain the bots have.
+pub struct World<B: Brain = MepBrain> {
+    pub deps: Graph<B>,
+    pub rng: SimRng,
+    pub config: SimConfig,
+    /// The instructions mutation draws from, as weighted by the config.
+    pub opcodes: OpcodeTable,
+    pub period: u64,
+    pub resets: i64,
+    /// The number of bots born by division since the run began.
+    pub divide_births: u64,
+    /// The number of bots born by mating since the run began.
+    pub mate_births: u64,
+    /// The number of bots that died since the run began.
+    pub deaths: i64,
+    /// The number of times bots moved to another node since the run began.
+    pub moves: i64,
+    /// The ID the next node made will have.
+    pub next_node_id: u64,
+    /// Bot IDs and birth and death events.
+    pub lineage: Lineage,
+    /// Print verbose information about every bot and the mesh while stepping.
+    pub print_info: bool,
+}
+
+impl<B: Brain> World<B> {
+    pub fn new(seed: &[u64], config: SimConfig) -> Self {
+        let weights = config.instruction_weights().unwrap_or_else(|e| panic!("invalid config: {}", e));
+        World {
+            deps: petgraph::Graph::new_undirected(),
+            rng: SimRng::new(seed),
+            config: config,
+            opcodes: OpcodeTable::new(&weights),
+            period: 0,
+            resets: -1,
+            divide_births: 0,
+            mate_births: 0,
+            deaths: 0,
+            moves: 0,
+            next_node_id: 0,
+            lineage: Lineage::new(),
+            print_info: false,
+        }
+    }
+
+    /// Run one tick of the simulation.
+    pub fn step(&mut self) {
+        self.step_observed(|_, _| ());
+    }
+
+    /// Run one tick of the simulation, calling `observe` with the world after each phase of the tick completes.
+    pub fn step_observed<F>(&mut self, mut observe: F)
+        where F: FnMut(Phase, &World<B>)
+    {
+        //Add node if none exist
+        if self.deps.node_count() == 0 {
+            let id = self.next_node_id;
+            self.next_node_id += 1;
+            self.deps.add_node(Node::new(id, self.config.node_starting_energy, zoom::BasicParticle::default()));
+            self.resets += 1;
+        }
+
+        self.spring();
+        self.grow();
+        observe(Phase::Growth, self);
+        self.split();
+        self.obliterate();
+        self.prune_edges();
+        observe(Phase::Split, self);
+        self.diffuse();
+        observe(Phase::Diffusion, self);
+        let disconnect_indices = self.update_bots();
+        self.disconnect(disconnect_indices);
+        observe(Phase::Bots, self);
+        self.interact();
+        observe(Phase::Connection, self);
+        self.settle();
+        observe(Phase::Settle, self);
+
+        //Print things out
+        if self.print_info {
+            self.print_summary();
+        }
+
+        self.period += 1;
+    }
+
+    /// Print the node, edge and reset counts of the mesh.
+    pub fn print_summary(&self) {
+        println!("Nodes: {}, Edges: {}, Resets: {}", self.deps.node_count(), self.deps.edge_count(), self.resets);
+    }
+
+    fn spring(&mut self) {
+        let (deps, config) = (&mut self.deps, &self.config);
+        let space = config.node_space();
+        let comp_delta = |ps: (Vec3, Vec3)| {
+            use zoom::Toroid;
+            space.wrap_delta(ps.1 - ps.0)
+        };
+        //Update forces between nodes on the correct periods
+        for i in deps.edge_indices() {
+            let node_indices = deps.edge_endpoints(i).unwrap();
+            let nodes = deps.index_twice_mut(node_indices.0, node_indices.1);
+
+            //Apply spring forces to keep them together
+            zoom::hooke_delta(&nodes.0.particle, &nodes.1.particle, config.attraction_magnitude /
+                (nodes.0.connections as f64 * nodes.1.connections as f64).sqrt(), &comp_delta);
+        }
+    }
+
+    fn grow(&mut self) {
+        let (deps, rng, config) = (&mut self.deps, &mut self.rng, &self.config);
+        let opcodes = &self.opcodes;
+        let (lineage, period) = (&mut self.lineage, self.period);
+        let nc = deps.node_count();
+        for n in deps.node_weights_mut() {
+            if nc < config.energy_cutoff_at {
+                n.grow(false, nc, rng, config);
+            } else {
+                n.grow(true, nc, rng, config);
+                if n.bots.len() == 0 {
+                    if rng.gen_range(0.0, 1.0) < config.empty_node_full_mesh_spawn_rate {
+                        n.bots.push(spawn_bot(rng, opcodes, config, lineage, period));
+                    }
+                }
+            }
+        }
+    }
+
+    fn split(&mut self) {
+        let (deps, rng, config) = (&mut self.deps, &mut self.rng, &self.config);
+        let opcodes = &self.opcodes;
+        let (lineage, period) = (&mut self.lineage, self.period);
+        let next_node_id = &mut self.next_node_id;
+
+        //Determine how many nodes will spawn
+        let spawners = rng.gen_range(0.0, (config.spawn_rate() * deps.node_count() as f64).powi(2)) as usize;
+        let mut spawn_places = (0..spawners).map(|_| Rank{rank: rng.gen_range(0, deps.node_count() as i64), data: ()}).collect::<BinaryHeap<_>>();
+
+        //Update nodes
+        for (ix, i) in deps.node_indices().enumerate() {
+            if deps[i].should_split(config) {
+                use std::f64::consts::PI;
+                use num::traits::Float;
+                let theta = rng.gen_range(0.0, 2.0 * PI);
+                let phi = rng.gen_range(-1.0, 1.0).acos();
+                let rand_unit_dir = Vec3::new(
+                    theta.cos() * phi.sin(),
+                    theta.sin() * phi.sin(),
+                    phi.cos(),
+                );
+
+                //Divide energy in half before splitting
+                deps[i].energy /= 2;
+
+                let nnode = {
+                    let nref = &deps[i];
+                    let id = *next_node_id;
+                    *next_node_id += 1;
+                    Node::new(
+                        id,
+                        nref.energy,
+                        nref.particle.p.clone(),
+                    )
+                };
+
+                let newindex = deps.add_node(nnode);
+                //Add all of the old node's neighbors
+                let it = sorted_neighbors(deps, i);
+                for iin in it {
+                    if rng.gen_range(0.0, 1.0) < 0.5 {
+                        deps.add_edge(newindex, iin, ());
+                        let ed = deps.find_edge(iin, i).unwrap();
+                        deps.remove_edge(ed);
+                    }
+                }
+
+                for ib in (0..deps[i].bots.len()).rev() {
+                    if rng.gen_range(0.0, 1.0) < 0.5 {
+                        let b = deps[i].bots.swap_remove(ib);
+                        deps[newindex].bots.push(b);
+                    }
+                }
+
+                //Add the old node as a neighbor
+                deps.add_edge(i, newindex, ());
+
+                //Add a positive impulse to this particle
+                deps[i].particle.p.velocity =
+                    deps[i].particle.p.velocity +
+                    rand_unit_dir * config.separation_magnitude;
+
+                //Add a negative impulse to the other particle
+                deps[newindex].particle.p.velocity =
+                    deps[newindex].particle.p.velocity -
+                    rand_unit_dir * config.separation_magnitude;
+
+                //Move the particles far enough away from each other so they can stay connected
+                deps[i].particle.p.position =
+                    deps[i].particle.p.position + rand_unit_dir * config.separation_delta;
+                deps[newindex].particle.p.position =
+                    deps[newindex].particle.p.position - rand_unit_dir * config.separation_delta;
+
+                //Add new bots to the nodes
+                for _ in 0..config.new_node_spawns {
+                    deps[i].bots.push(spawn_bot(rng, opcodes, config, lineage, period));
+                    deps[newindex].bots.push(spawn_bot(rng, opcodes, config, lineage, period));
+                }
+            }
+
+            while let Some(&Rank{rank: ri, ..}) = spawn_places.peek() {
+                if ri as usize == ix {
+                    deps[i].bots.push(spawn_bot(rng, opcodes, config, lineage, period));
+                    spawn_places.pop();
+                } else {
+                    break;
+                }
+            }
+        }
+    }
+
+    fn obliterate(&mut self) {
+        let (deps, rng, config) = (&mut self.deps, &mut self.rng, &self.config);
+        let (lineage, period) = (&mut self.lineage, self.period);
+        //Update obliteration
+        for i in deps.node_indices().rev() {
+            deps[i].advance(config);
+            //Reset connections here before next loop
+            deps[i].connections = 0;
+            if deps[i].should_obliterate() {
+                let neighbors = sorted_neighbors(deps, i);
+                for ix in 0..neighbors.len() {
+                    for jx in (ix+1)..neighbors.len() {
+                        if rng.gen_range(0.0, 1.0) < config.connect_probability {
+                            deps.update_edge(neighbors[ix], neighbors[jx], ());
+                        }
+                    }
+                }
+                //Remove the edges from the highest index down so that the edges left keep predictable indices
+                let mut edges = neighbors.iter().map(|&n| deps.find_edge(i, n).unwrap()).collect_vec();
+                edges.sort();
+                for e in edges.into_iter().rev() {
+                    deps.remove_edge(e);
+                }
+                //The bots go down with the node
+                for b in deps[i].bots.iter().chain(deps[i].moved_bots.iter()) {
+                    lineage.death(&**b, period);
+                }
+                deps.remove_node(i);
+            }
+        }
+    }
+
+    fn prune_edges(&mut self) {
+        let (deps, config) = (&mut self.deps, &self.config);
+        for i in deps.edge_indices().rev() {
+            if let Some((i1, i2)) = deps.edge_endpoints(i) {
+                use zoom::{Position, Vector};
+                let mag = (deps[i1].particle.position() - deps[i2].particle.position()).displacement_squared();
+                if mag > config.connect_max_length().powi(2)/* || mag < CONNECT_MIN_LENGTH.powi(2)*/ {
+                    deps.remove_edge(i);
+                } else {
+                    deps[i1].connections += 1;
+                    deps[i2].connections += 1;
+                }
+            }
+        }
+    }
+
+    fn diffuse(&mut self) {
+        let (deps, config) = (&mut self.deps, &self.config);
+        //Update diffusion first pass
+        for i in deps.node_indices() {
+            deps[i].diffuse(config);
+        }
+
+        //Update diffusion second pass
+        for i in deps.node_indices() {
+            let neighbors = sorted_neighbors(deps, i);
+            for n in neighbors {
+                deps[i].energy += deps[n].diffuse / deps[n].connections;
+            }
+        }
+    }
+
+    /// Run all of the bot brains, perform matings and movements, and return the edges bots chose to sever.
+    ///
+    /// The brains of all nodes run in parallel on what the world was like at the start of the phase and then what the
+    /// bots decided is acted on one node at a time in index order. The random input of each node is drawn from the
+    /// world's generator in that order before any brain runs, so the result doesn't depend on the number of threads.
+    ///
+    /// This stands in for giving every node its own generator derived from the seed, the tick and the node: a single
+    /// generator is all a snapshot has to record, and only the one random input per node is needed before acting
+    /// goes back to drawing serially. Drawing the inputs up front rather than between the nodes acting does change
+    /// the numbers every later draw gets, so a seed doesn't reproduce the runs it gave before brains ran in parallel.
+    fn update_bots(&mut self) -> Vec<(NodeIndex, NodeIndex)> {
+        let (deps, rng, config) = (&mut self.deps, &mut self.rng, &self.config);
+        let opcodes = &self.opcodes;
+        let print_info = self.print_info;
+        let (divide_births, mate_births) = (&mut self.divide_births, &mut self.mate_births);
+        let (lineage, period) = (&mut self.lineage, self.period);
+
+        let nodes = deps.node_indices()
+            .map(|i| {
+                //Make rng value on a node basis to avoid insane clustering
+                let rngval: i64 = rng.gen();
+                //The current node is always 0; everything else comes after
+                let neighbors = ::std::iter::once(i).chain(sorted_neighbors(deps, i)).collect_vec();
+                (i, rngval, neighbors)
+            })
+            .collect_vec();
+
+        let thoughts = {
+            let deps = &*deps;
+            nodes.par_iter()
+                .map(|&(i, rngval, ref neighbors)| think(deps, i, neighbors, rngval, period, config))
+                .collect::<Vec<_>>()
+        };
+
+        let mut disconnect_indices = Vec::new();
+
+        //Update bots in nodes
+        for ((i, _, neighbors), thoughts) in nodes.into_iter().zip(thoughts) {
+            //Set pull to 0 before accumulating
+            deps[i].pull = 0;
+
+            let mut movers = BinaryHeap::<usize>::new();
+            let mut maters = Vec::<usize>::new();
+
+            for (ib, thought) in thoughts.into_iter().enumerate() {
+                {
+                    let mb = &mut *deps[i].bots[ib];
+                    mb.decision = thought.decision;
+                    mb.memory = thought.memory;
+                }
+                {
+                    let mb = &*deps[i].bots[ib];
+                    if print_info {
+                        println!("Bot energy {}, brain sizes {}, {}, {} \
+                            decided to consume {}, move {}, mate {}, \
+                            signal {}, sever {}, pull {}, and connect {}",
+                            mb.energy,
+                            mb.node_brain.len(),
+                            mb.bot_brain.len(),
+                            mb.final_brain.len(),
+                            mb.decision.rate, mb.decision.node, mb.decision.mate,
+                            mb.decision.signal, mb.decision.sever_choice,
+                            mb.decision.pull,
+                            mb.decision.connect_signal);
+                    }
+                    if mb.decision.mate >= 0 && mb.decision.mate < deps[i].bots.len() as i64 && mb.energy >= config.mate_energy {
+                        maters.push(ib);
+                    }
+                    //Node 0 is not included because that is the present node
+                    if mb.decision.node > 0 && mb.decision.node < neighbors.len() as i64 {
+                        movers.push(ib);
+                    }
+                }
+                //Accumulate pull from bot (shift bots to avoid ordering issues)
+                deps[i].pull = deps[i].pull.saturating_add(deps[i].bots[ib].decision.pull >> 8);
+
+                let choice = deps[i].bots[ib].decision.sever_choice;
+                if choice > 0 && choice < neighbors.len() as i64 {
+                    disconnect_indices.push((i, neighbors[choice as usize]));
+                }
+            }
+
+            //Perform the matings on the node
+            for ib in maters {
+                if deps[i].bots[ib].decision.mate as usize == ib {
+                    let mut nbot = Box::new(deps[i].bots[ib].divide(rng, opcodes, config));
+                    lineage.birth(&mut *nbot, period);
+                    deps[i].bots.push(nbot);
+                    *divide_births += 1;
+                } else {
+                    let gn = &mut deps[i];
+                    //Do this unsafely because we know the indices are in bounds and not the same
+                    let mut nbot = Box::new(unsafe{
+                        let bm = &mut *(gn.bots.get_unchecked_mut(ib) as *mut Box<Bot<B>>);
+                        let bo = gn.bots.get_unchecked_mut(bm.decision.mate as usize);
+                        bm.mate(bo, rng, opcodes, config)
+                    });
+                    lineage.birth(&mut *nbot, period);
+                    gn.bots.push(nbot);
+                    *mate_births += 1;
+                }
+            }
+
+            //Move bots to the node they desire starting from the end of the vector to avoid swaps
+            while let Some(ib) = movers.pop() {
+                let n = deps[i].bots[ib].decision.node;
+                let b = deps[i].bots.swap_remove(ib);
+                deps[neighbors[n as usize]].moved_bots.push(b);
+            }
+        }
+
+        disconnect_indices
+    }
+
+    fn disconnect(&mut self, disconnect_indices: Vec<(NodeIndex, NodeIndex)>) {
+        //Deconnect nodes
+        for n in disconnect_indices {
+            match self.deps.find_edge(n.0, n.1) {
+                Some(e) => {self.deps.remove_edge(e);},
+                None => {},
+            }
+        }
+    }
+
+    fn interact(&mut self) {
+        let (deps, config) = (&mut self.deps, &self.config);
+        let space = config.node_space();
+        let comp_delta = |ps: (Vec3, Vec3)| {
+            use zoom::Toroid;
+            space.wrap_delta(ps.1 - ps.0)
+        };
+        //Update particle forces between each node
+        {
+            use zoom::Position;
+            let nodes = deps.raw_nodes();
+            let positions = nodes.iter().map(|n| n.weight.particle.position()).collect_vec();
+            //Pull or push particles depending on the factors
+            let pull = |i: usize, j: usize| {
+                config.bot_pull_magnitude *
+                (nodes[i].weight.bots.len() as f64 * config.sig(nodes[i].weight.pull) +
+                 nodes[j].weight.bots.len() as f64 * config.sig(nodes[j].weight.pull))
+            };
+            if config.barnes_hut_theta > 0.0 {
+                //The space reaches its offset to either side of its origin
+                let tree = Octree::new(&positions, space.origin - space.offset, 2.0 * space.offset.x);
+                //Repulse particles to keep them apart from each other, approximating distant groups as one particle
+                for i in 0..nodes.len() {
+                    tree.interactions(&positions, i, config.barnes_hut_theta, |a, b| comp_delta((a, b)), |p, count| {
+                        //The other side feels this force when its own interactions are visited
+                        let mut other = zoom::BasicParticle::default();
+                        other.position = p;
+                        zoom::gravitate_radius_delta(&nodes[i].weight.particle, &RadParticle { p: other },
+                            -config.repulsion_magnitude * count as f64, &comp_delta);
+                    });
+                }
+                if config.bot_pull_radius > 0.0 {
+                    for (i, j) in Grid::new(&positions, config.bot_pull_radius).pairs(&positions) {
+                        zoom::gravitate_radius_delta(&nodes[i].weight.particle, &nodes[j].weight.particle,
+                            pull(i, j), &comp_delta);
+                    }
+                }
+            } else {
+                for i in 0..nodes.len() {
+                    use na::Norm;
+                    for j in (i+1)..nodes.len() {
+                        let mag_s = (positions[i] - positions[j]).sqnorm();
+                        //Apply all gravitation forces
+                        zoom::gravitate_radius_delta(&nodes[i].weight.particle, &nodes[j].weight.particle,
+                            //Repulse particles to keep them apart from each other
+                            -config.repulsion_magnitude +
+                            //Attract particles based on the amount of bots in them
+                            //BOT_GRAVITATION_MAGNITUDE *
+                            //((nodes[i].weight.bots.len() + nodes[j].weight.bots.len()) as f64) +
+                            if mag_s < config.bot_pull_radius * config.bot_pull_radius {
+                                pull(i, j)
+                            } else {
+                                0.0
+                            },
+                            &comp_delta
+                        );
+                    }
+                }
+            }
+        }
+
+        //Now connect the nodes as necessary
+        for (ix, jx) in plan_connections(deps, config) {
+            deps.update_edge(NodeIndex::new(ix), NodeIndex::new(jx), ());
+        }
+    }
+
+    fn settle(&mut self) {
+        let (deps, rng, config) = (&mut self.deps, &mut self.rng, &self.config);
+        let opcodes = &self.opcodes;
+        let (lineage, period) = (&mut self.lineage, self.period);
+        let (deaths, moves) = (&mut self.deaths, &mut self.moves);
+        let print_info = self.print_info;
+        //Update all nodes with bot movements and memory, etc
+        for i in deps.node_indices() {
+            let n = &mut deps[i];
+            n.moves = n.moved_bots.len() as i64;
+            while let Some(b) = n.moved_bots.pop() {
+                n.bots.push(b);
+            }
+            n.deaths = 0;
+            for ib in (0..n.bots.len()).rev() {
+                n.bots[ib].cycle(config);
+                if rng.gen_range(0.0, 1.0) < config.mutation_rate {
+                    n.bots[ib].mutate(rng, opcodes, config);
+                }
+                //Remove any dead bots
+                if n.bots[ib].energy <= 0 {
+                    lineage.death(&*n.bots[ib], period);
+                    n.bots.swap_remove(ib);
+                    n.deaths += 1;
+                }
+            }
+            *deaths += n.deaths;
+            *moves += n.moves;
+            //Consume energy after loosing some so bots can reach max
+            for b in n.bots.iter_mut() {
+                let mut asking = (config.sig(b.decision.rate) * config.energy_exchange_magnitude as f64) as i64;
+                // Don't let a bot provide more energy than it has
+                if -asking > b.energy {
+                    asking = -b.energy;
+                }
+                b.energy = b.energy.saturating_add(asking);
+                n.energy = n.energy.saturating_sub(asking);
+                if asking > 0 {
+                    b.consumed = b.consumed.saturating_add(asking);
+                }
+                if b.energy > config.max_energy {
+                    if print_info {
+                        println!("Bot went over max to {}", b.energy);
+                    }
+                    b.energy = config.max_energy;
+                }
+            }
+            // Shrink vector to prevent insane memory usage
+            n.bots.shrink_to_fit();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rayon::ThreadPoolBuilder;
+
+    use super::World;
+    use config::SimConfig;
+    use replay::TickDigest;
+    use SEED;
+
+    fn digests(threads: usize) -> Vec<TickDigest> {
+        let pool = ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+        pool.install(|| {
+            // Spawn bots early so that there are brains to run in parallel
+            let mut config = SimConfig::default();
+            config.start_spawning_at = 10;
+            let mut world: World = World::new(&SEED, config);
+            (0..500).map(|_| TickDigest::step(&mut world)).collect()
+        })
+    }
+
+    #[test]
+    fn thread_count_does_not_change_results() {
+        let single = digests(1);
+        assert_eq!(digests(4), single);
+    }
+}